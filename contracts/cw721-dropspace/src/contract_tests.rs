@@ -1,18 +1,31 @@
 #![cfg(test)]
-use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MOCK_CONTRACT_ADDR};
 
 use cosmwasm_std::{
-    coin, DepsMut, Empty, Addr, from_json, Response
+    attr, coin, DepsMut, Empty, Addr, Binary, from_json, Order, Response
 };
+use cosmwasm_std::{to_json_binary, BankMsg, Coin, SubMsg, Uint128, WasmMsg};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, Denom};
 
 use cw721::{
-    ContractInfoResponse, Cw721Query, OwnerOfResponse
+    ContractInfoResponse, Cw721Query, Expiration, OrderBy, OwnerOfResponse, TokensResponse
 };
-use cw721_base_016::entry::query;
 use cw_ownable::OwnershipError;
+use k256::ecdsa::{signature::hazmat::PrehashSigner, Signature, SigningKey};
 
+use crate::msg::{
+    AllTokensInfoResponse, NftInfoBatchResponse, OwnerOfBatchResponse, BalanceResponse, BlocklistResponse, ConfigUpdate, Holder, HoldersResponse, NumHoldersResponse,
+    OperatorFilterResponse, ReceiveMsg,
+    ReservedAmountResponse, SaleStatusResponse, SoftCapInfoResponse, StatesResponse,
+    SupplyInfoResponse, TokenByIndexResponse, TokenHistoryEntry, TokenHistoryResponse,
+};
+use crate::state::{
+    pubkey_address, BuyerStats, Commitment, Config, Escrow, EscrowedPayment, HistoryAction,
+    HistoryRecord, OperatorFilter, PermitPayload, PurchaseMode, RecipeInput, SaleStats,
+    SaleStatus, TokenIdScheme, VoucherPayload,
+};
 use crate::{
-    query, ContractError, Cw721Contract, ExecuteMsg, Extension, InstantiateMsg, MinterResponse, QueryMsg
+    ContractError, Cw721Contract, ExecuteMsg, Extension, InstantiateMsg, MinterResponse, QueryMsg
 };
 
 const MINTER: &str = "merlin";
@@ -36,7 +49,21 @@ fn setup_contract(deps: DepsMut<'_>) -> Cw721Contract<'static, Extension, Empty,
         supply_limit: Some(10000u64),
         reserved_amount: Some(0u64),
         dev_wallet: None,
-        sale_time: None
+        sale_time: None,
+        history_depth: None,
+        burns_free_supply: None,
+        token_id_scheme: None,
+        uri_template: None,
+        uri_suffix: None,
+        max_reserved: None,
+        sale_end_time: None,
+        soft_cap: None,
+        purchase_mode: None,
+        max_per_block: None,
+        buy_cooldown: None,
+        operator_filter: None,
+        compliance: None,
+        voucher_signer: None,
     };
     let info = mock_info("creator", &[]);
     let res = contract.instantiate(deps, mock_env(), info, msg).unwrap();
@@ -62,7 +89,21 @@ fn proper_instantiation() {
         supply_limit: Some(10000u64),
         reserved_amount: Some(0u64),
         dev_wallet: None,
-        sale_time: None
+        sale_time: None,
+        history_depth: None,
+        burns_free_supply: None,
+        token_id_scheme: None,
+        uri_template: None,
+        uri_suffix: None,
+        max_reserved: None,
+        sale_end_time: None,
+        soft_cap: None,
+        purchase_mode: None,
+        max_per_block: None,
+        buy_cooldown: None,
+        operator_filter: None,
+        compliance: None,
+        voucher_signer: None,
     };
     let info = mock_info("creator", &[]);
 
@@ -85,16 +126,17 @@ fn proper_instantiation() {
     );
 
     let withdraw_address = contract
-        .withdraw_address
-        .may_load(deps.as_ref().storage)
-        .unwrap();
+        .config
+        .load(deps.as_ref().storage)
+        .unwrap()
+        .withdraw_address;
     assert_eq!(Some(MINTER.to_string()), withdraw_address);
 
     let count = contract.num_tokens(deps.as_ref()).unwrap();
     assert_eq!(0, count.count);
 
     // list the token_ids
-    let tokens = contract.all_tokens(deps.as_ref(), None, None).unwrap();
+    let tokens = contract.all_tokens(deps.as_ref(), None, None, None, None).unwrap();
     assert_eq!(0, tokens.tokens.len());
 }
 
@@ -120,8 +162,8 @@ fn set_get_dev_wallet() {
         .execute(deps.as_mut(), mock_env(), owner, set_dev_wallet_msg)
         .unwrap();
 
-    let res = contract.dev_wallet.may_load(deps.as_ref().storage).unwrap_or_default();
-    assert_eq!(Some("recipient".to_string()), res);
+    let res = contract.config.load(deps.as_ref().storage).unwrap().dev_wallet;
+    assert_eq!("recipient".to_string(), res);
 }
 
 #[test]
@@ -142,12 +184,13 @@ fn set_get_base_uri() {
     // owner can
     let owner = mock_info("merlin", &[]);
 
-    contract
+    let res = contract
         .execute(deps.as_mut(), mock_env(), owner, set_base_uri_msg)
         .unwrap();
+    assert_eq!(res.attributes[0], attr("action", "set_base_uri"));
 
-    let res = contract.base_uri.may_load(deps.as_ref().storage).unwrap_or_default();
-    assert_eq!(Some("https://randomUri/".into()), res);
+    let res = contract.config.load(deps.as_ref().storage).unwrap().base_uri;
+    assert_eq!("https://randomUri/".to_string(), res);
 }
 
 #[test]
@@ -169,11 +212,17 @@ fn set_get_mint_per_tx() {
     let owner = mock_info("merlin", &[]);
 
     contract
-        .execute(deps.as_mut(), mock_env(), owner, set_mint_per_tx_msg)
+        .execute(deps.as_mut(), mock_env(), owner.clone(), set_mint_per_tx_msg)
         .unwrap();
 
-    let res = contract.mint_per_tx.may_load(deps.as_ref().storage).unwrap_or_default();
-    assert_eq!(Some(100u64), res);
+    let res = contract.config.load(deps.as_ref().storage).unwrap().mint_per_tx;
+    assert_eq!(100u64, res);
+
+    // validated like UpdateConfig
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), owner, ExecuteMsg::SetMintPerTx { tx: 0 })
+        .unwrap_err();
+    assert_eq!(err, ContractError::ZeroMintPerTx {});
 }
 
 #[test]
@@ -198,8 +247,8 @@ fn set_get_mint_fee() {
         .execute(deps.as_mut(), mock_env(), owner, set_mint_fee_msg)
         .unwrap();
 
-    let res = contract.mint_fee.may_load(deps.as_ref().storage).unwrap_or_default();
-    assert_eq!(Some(100u64), res);
+    let res = contract.config.load(deps.as_ref().storage).unwrap().mint_fee;
+    assert_eq!(100u64, res);
 }
 
 #[test]
@@ -224,8 +273,8 @@ fn set_get_dev_fee() {
         .execute(deps.as_mut(), mock_env(), owner, set_dev_fee_msg)
         .unwrap();
 
-    let res = contract.dev_fee.may_load(deps.as_ref().storage).unwrap_or_default();
-    assert_eq!(Some(100u64), res);
+    let res = contract.config.load(deps.as_ref().storage).unwrap().dev_fee;
+    assert_eq!(100u64, res);
 }
 
 #[test]
@@ -247,11 +296,35 @@ fn set_get_supply_limit() {
     let owner = mock_info("merlin", &[]);
 
     contract
-        .execute(deps.as_mut(), mock_env(), owner, set_supply_limit_msg)
+        .execute(deps.as_mut(), mock_env(), owner.clone(), set_supply_limit_msg)
         .unwrap();
 
-    let res = contract.supply_limit.may_load(deps.as_ref().storage).unwrap_or_default();
-    assert_eq!(Some(10000u64), res);
+    let res = contract.config.load(deps.as_ref().storage).unwrap().supply_limit;
+    assert_eq!(10000u64, res);
+
+    // validated like UpdateConfig
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::Reserve {
+                qty: 2,
+                extension: None,
+                recipient: None,
+            },
+        )
+        .unwrap();
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), owner, ExecuteMsg::SetSupplyLimit { supply_limit: 1 })
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::SupplyLimitTooLow {
+            supply_limit: 1,
+            total_supply: 2,
+        }
+    );
 }
 
 #[test]
@@ -276,8 +349,8 @@ fn set_get_sale_time() {
         .execute(deps.as_mut(), mock_env(), owner, set_sale_time_msg)
         .unwrap();
 
-    let res = contract.sale_time.may_load(deps.as_ref().storage).unwrap_or_default();
-    assert_eq!(Some(100u64), res);
+    let res = contract.config.load(deps.as_ref().storage).unwrap().sale_time;
+    assert_eq!(100u64, res);
 }
 
 #[test]
@@ -414,7 +487,11 @@ fn test_update_ownership() {
     let mut deps = mock_dependencies();
     let contract = setup_contract(deps.as_mut());
 
-    let reserve_msg = ExecuteMsg::Reserve { qty: 1, extension: None } ;
+    let reserve_msg = ExecuteMsg::Reserve {
+        qty: 1,
+        extension: None,
+        recipient: None,
+    } ;
 
     // Minter can mint
     let minter_info = mock_info(MINTER, &[]);
@@ -502,6 +579,7 @@ fn transferring_nft() {
     let reserve_msg = ExecuteMsg::Reserve {
         qty: 1,
         extension: None,
+        recipient: None,
     };
 
     let minter = mock_info(MINTER, &[]);
@@ -550,4 +628,3085 @@ fn transferring_nft() {
             approvals: vec![],
         }
     );
-}
\ No newline at end of file
+}
+#[test]
+fn redeeming_recipe() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+
+    let minter = mock_info(MINTER, &[]);
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter.clone(),
+            ExecuteMsg::Reserve {
+                qty: 3,
+                extension: None,
+                recipient: None,
+            },
+        )
+        .unwrap();
+
+    let add_recipe_msg = ExecuteMsg::AddRecipe {
+        inputs: vec![RecipeInput {
+            count: 2,
+            extension: None,
+        }],
+        output_uri: Some("https://rare/1.json".to_string()),
+        output_extension: None,
+        supply_cap: Some(1),
+        count_in_supply: false,
+    };
+
+    // random cannot add a recipe
+    let random = mock_info("random", &[]);
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), random.clone(), add_recipe_msg.clone())
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+
+    // owner can
+    contract
+        .execute(deps.as_mut(), mock_env(), minter.clone(), add_recipe_msg)
+        .unwrap();
+
+    // one token is not enough
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter.clone(),
+            ExecuteMsg::Redeem {
+                recipe_id: 1,
+                token_ids: vec!["Magic #1".to_string()],
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::RecipeMismatch { recipe_id: 1 });
+
+    // random cannot burn the owner's tokens
+    let redeem_msg = ExecuteMsg::Redeem {
+        recipe_id: 1,
+        token_ids: vec!["Magic #1".to_string(), "Magic #2".to_string()],
+    };
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), random, redeem_msg.clone())
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+
+    // token owner can
+    contract
+        .execute(deps.as_mut(), mock_env(), minter.clone(), redeem_msg)
+        .unwrap();
+
+    let count = contract.num_tokens(deps.as_ref()).unwrap();
+    assert_eq!(2, count.count);
+    let info = contract
+        .nft_info(deps.as_ref(), "Magic #R1".to_string())
+        .unwrap();
+    assert_eq!(Some("https://rare/1.json".to_string()), info.token_uri);
+    let total_supply = contract.sale_state.load(deps.as_ref().storage).unwrap().total_supply;
+    assert_eq!(3, total_supply);
+
+    // the supply cap of the recipe is reached
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter.clone(),
+            ExecuteMsg::Redeem {
+                recipe_id: 1,
+                token_ids: vec!["Magic #3".to_string(), "Magic #R1".to_string()],
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::RecipeSoldOut { recipe_id: 1 });
+
+    // a redemption counted in the supply doesn't take a mint index
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter.clone(),
+            ExecuteMsg::AddRecipe {
+                inputs: vec![RecipeInput {
+                    count: 1,
+                    extension: None,
+                }],
+                output_uri: None,
+                output_extension: None,
+                supply_cap: None,
+                count_in_supply: true,
+            },
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter.clone(),
+            ExecuteMsg::Redeem {
+                recipe_id: 2,
+                token_ids: vec!["Magic #3".to_string()],
+            },
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter,
+            ExecuteMsg::Reserve {
+                qty: 1,
+                extension: None,
+                recipient: None,
+            },
+        )
+        .unwrap();
+    contract.tokens.load(&deps.storage, "Magic #4").unwrap();
+    let sale_state = contract.sale_state.load(&deps.storage).unwrap();
+    assert_eq!(sale_state.total_supply, 5);
+    assert_eq!(sale_state.mint_cursor, 4);
+}
+
+#[test]
+fn buying_with_cw20() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+
+    let owner = mock_info(MINTER, &[]);
+    contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), ExecuteMsg::ToggleSaleActive {})
+        .unwrap();
+
+    let receive_msg = |amount: u128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "buyer".to_string(),
+            amount: Uint128::new(amount),
+            msg: to_json_binary(&ReceiveMsg::Buy {
+                qty: 2,
+                extension: None::<Empty>,
+            })
+            .unwrap(),
+        })
+    };
+
+    // token is not accepted yet
+    let token = mock_info("token", &[]);
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), token.clone(), receive_msg(1000))
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::UnacceptedToken {
+            token: "token".to_string()
+        }
+    );
+
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner,
+            ExecuteMsg::SetCw20Price {
+                token: "token".to_string(),
+                mint_fee: Uint128::new(100),
+                dev_fee: Uint128::new(200),
+            },
+        )
+        .unwrap();
+
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), token.clone(), receive_msg(500))
+        .unwrap_err();
+    assert_eq!(err, ContractError::IncorrectFunds {});
+
+    // overpayment is refunded and the fees are split in the cw20 token
+    let res = contract
+        .execute(deps.as_mut(), mock_env(), token, receive_msg(700))
+        .unwrap();
+    let transfer = |recipient: &str, amount: u128| {
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "token".to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount: Uint128::new(amount),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    };
+    assert_eq!(
+        res.messages,
+        vec![
+            transfer("buyer", 100),
+            transfer(MINTER, 200),
+            transfer("creator", 400),
+        ]
+    );
+
+    let owner_res = contract
+        .owner_of(deps.as_ref(), mock_env(), "Magic #2".to_string(), false)
+        .unwrap();
+    assert_eq!("buyer", owner_res.owner);
+}
+
+#[test]
+fn buying_with_multiple_denoms() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+
+    let owner = mock_info(MINTER, &[]);
+    contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), ExecuteMsg::ToggleSaleActive {})
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::SetMintFee { fee: 100u64 },
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner,
+            ExecuteMsg::SetDenomPrice {
+                denom: "uusdc".to_string(),
+                mint_fee: Uint128::new(10),
+                dev_fee: Uint128::new(5),
+            },
+        )
+        .unwrap();
+
+    let prices: Vec<Coin> = from_json(
+        contract
+            .query(deps.as_ref(), mock_env(), QueryMsg::GetMintPrice {})
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(prices, vec![coin(100, "unibi"), coin(15, "uusdc")]);
+
+    let buy_msg = ExecuteMsg::Buy {
+        qty: 1,
+        extension: None,
+    };
+
+    // mixed denoms are rejected
+    let mixed = mock_info("buyer", &[coin(100, "unibi"), coin(15, "uusdc")]);
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), mixed, buy_msg.clone())
+        .unwrap_err();
+    assert_eq!(err, ContractError::MultipleDenoms {});
+
+    // unknown denoms are rejected
+    let unknown = mock_info("buyer", &[coin(100, "uatom")]);
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), unknown, buy_msg.clone())
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::UnacceptedDenom {
+            denom: "uatom".to_string()
+        }
+    );
+
+    // the split is paid out in the denom that was sent
+    let buyer = mock_info("buyer", &[coin(20, "uusdc")]);
+    let res = contract
+        .execute(deps.as_mut(), mock_env(), buyer, buy_msg)
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(BankMsg::Send {
+                to_address: "buyer".to_string(),
+                amount: vec![coin(5, "uusdc")],
+            }),
+            SubMsg::new(BankMsg::Send {
+                to_address: MINTER.to_string(),
+                amount: vec![coin(10, "uusdc")],
+            }),
+            SubMsg::new(BankMsg::Send {
+                to_address: "creator".to_string(),
+                amount: vec![coin(5, "uusdc")],
+            }),
+        ]
+    );
+}
+
+#[test]
+fn tracking_balances() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+
+    let minter = mock_info(MINTER, &[]);
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter.clone(),
+            ExecuteMsg::Reserve {
+                qty: 3,
+                extension: None,
+                recipient: None,
+            },
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter.clone(),
+            ExecuteMsg::TransferNft {
+                recipient: "random".to_string(),
+                token_id: "Magic #1".to_string(),
+            },
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter,
+            ExecuteMsg::Burn {
+                token_id: "Magic #2".to_string(),
+            },
+        )
+        .unwrap();
+
+    let balance = |deps: cosmwasm_std::Deps, owner: &str| -> u64 {
+        let res: BalanceResponse = from_json(
+            contract
+                .query(
+                    deps,
+                    mock_env(),
+                    QueryMsg::Balance {
+                        owner: owner.to_string(),
+                    },
+                )
+                .unwrap(),
+        )
+        .unwrap();
+        res.balance
+    };
+    assert_eq!(1, balance(deps.as_ref(), MINTER));
+    assert_eq!(1, balance(deps.as_ref(), "random"));
+    assert_eq!(0, balance(deps.as_ref(), "nobody"));
+}
+
+#[test]
+fn listing_holders() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+
+    let minter = mock_info(MINTER, &[]);
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter.clone(),
+            ExecuteMsg::Reserve {
+                qty: 3,
+                extension: None,
+                recipient: None,
+            },
+        )
+        .unwrap();
+    for (recipient, token_id) in [("alice", "Magic #1"), ("bob", "Magic #2"), ("bob", "Magic #3")] {
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                minter.clone(),
+                ExecuteMsg::TransferNft {
+                    recipient: recipient.to_string(),
+                    token_id: token_id.to_string(),
+                },
+            )
+            .unwrap();
+    }
+
+    let holders: HoldersResponse = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::Holders {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        holders.holders,
+        vec![
+            Holder {
+                address: "alice".to_string(),
+                count: 1
+            },
+            Holder {
+                address: "bob".to_string(),
+                count: 2
+            },
+        ]
+    );
+
+    // an address that no longer holds anything is dropped
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Burn {
+                token_id: "Magic #1".to_string(),
+            },
+        )
+        .unwrap();
+    let num_holders: NumHoldersResponse = from_json(
+        contract
+            .query(deps.as_ref(), mock_env(), QueryMsg::NumHolders {})
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(1, num_holders.count);
+    let holders: HoldersResponse = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::Holders {
+                    start_after: Some("alice".to_string()),
+                    limit: Some(1),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        holders.holders,
+        vec![Holder {
+            address: "bob".to_string(),
+            count: 2
+        }]
+    );
+}
+
+#[test]
+fn recording_token_history() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+
+    let minter = mock_info(MINTER, &[]);
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter.clone(),
+            ExecuteMsg::Reserve {
+                qty: 1,
+                extension: None,
+                recipient: None,
+            },
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            minter,
+            ExecuteMsg::TransferNft {
+                recipient: "random".to_string(),
+                token_id: "Magic #1".to_string(),
+            },
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("random", &[]),
+            ExecuteMsg::SendNft {
+                contract: "market".to_string(),
+                token_id: "Magic #1".to_string(),
+                msg: to_json_binary("list").unwrap(),
+            },
+        )
+        .unwrap();
+
+    let history = |deps: cosmwasm_std::Deps| -> Vec<TokenHistoryEntry> {
+        let res: TokenHistoryResponse = from_json(
+            contract
+                .query(
+                    deps,
+                    mock_env(),
+                    QueryMsg::TokenHistory {
+                        token_id: "Magic #1".to_string(),
+                        start_after: None,
+                        limit: None,
+                    },
+                )
+                .unwrap(),
+        )
+        .unwrap();
+        res.history
+    };
+    let block = mock_env().block;
+    let merlin = Addr::unchecked(MINTER);
+    let random = Addr::unchecked("random");
+    let market = Addr::unchecked("market");
+    assert_eq!(
+        history(deps.as_ref()),
+        vec![
+            TokenHistoryEntry {
+                seq: 0,
+                record: HistoryRecord::new(
+                    HistoryAction::Mint,
+                    &merlin,
+                    None,
+                    Some(&merlin),
+                    &block
+                ),
+            },
+            TokenHistoryEntry {
+                seq: 1,
+                record: HistoryRecord::new(
+                    HistoryAction::Transfer,
+                    &merlin,
+                    Some(&merlin),
+                    Some(&random),
+                    &block
+                ),
+            },
+            TokenHistoryEntry {
+                seq: 2,
+                record: HistoryRecord::new(
+                    HistoryAction::Send,
+                    &random,
+                    Some(&random),
+                    Some(&market),
+                    &block
+                ),
+            },
+        ]
+    );
+
+    // a depth of 0 removes the cap
+    let res = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::SetHistoryDepth { depth: Some(0) },
+        )
+        .unwrap();
+    assert_eq!(res.attributes[1], attr("history_depth", "unlimited"));
+    assert!(contract.history_depth.may_load(&deps.storage).unwrap().is_none());
+
+    // only the most recent records are kept once the depth is capped
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::SetHistoryDepth { depth: Some(2) },
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("market", &[]),
+            ExecuteMsg::Burn {
+                token_id: "Magic #1".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        history(deps.as_ref()),
+        vec![
+            TokenHistoryEntry {
+                seq: 2,
+                record: HistoryRecord::new(
+                    HistoryAction::Send,
+                    &random,
+                    Some(&random),
+                    Some(&market),
+                    &block
+                ),
+            },
+            TokenHistoryEntry {
+                seq: 3,
+                record: HistoryRecord::new(
+                    HistoryAction::Burn,
+                    &market,
+                    Some(&market),
+                    None,
+                    &block
+                ),
+            },
+        ]
+    );
+}
+
+#[test]
+fn collecting_sale_stats() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+
+    let owner = mock_info(MINTER, &[]);
+    for msg in [
+        ExecuteMsg::ToggleSaleActive {},
+        ExecuteMsg::SetMintFee { fee: 100u64 },
+        ExecuteMsg::SetDevFee { fee: 10u64 },
+        ExecuteMsg::SetMintPerTx { tx: 5 },
+        ExecuteMsg::SetDenomPrice {
+            denom: "uusdc".to_string(),
+            mint_fee: Uint128::new(10),
+            dev_fee: Uint128::new(5),
+        },
+    ] {
+        contract
+            .execute(deps.as_mut(), mock_env(), owner.clone(), msg)
+            .unwrap();
+    }
+
+    let buy = |qty| ExecuteMsg::Buy {
+        qty,
+        extension: None,
+    };
+    contract
+        .execute(deps.as_mut(), mock_env(), mock_info("alice", &[coin(220, "unibi")]), buy(2))
+        .unwrap();
+    contract
+        .execute(deps.as_mut(), mock_env(), mock_info("alice", &[coin(15, "uusdc")]), buy(1))
+        .unwrap();
+    contract
+        .execute(deps.as_mut(), mock_env(), mock_info("bob", &[coin(110, "unibi")]), buy(1))
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner,
+            ExecuteMsg::Reserve {
+                qty: 1,
+                extension: None,
+                recipient: None,
+            },
+        )
+        .unwrap();
+
+    let stats: SaleStats = from_json(
+        contract
+            .query(deps.as_ref(), mock_env(), QueryMsg::SaleStats {})
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        stats,
+        SaleStats {
+            buyers: 2,
+            sold: 4,
+            reserved: 1,
+            revenue: vec![coin(330, "unibi"), coin(15, "uusdc")],
+            mint_fees: vec![coin(300, "unibi"), coin(10, "uusdc")],
+            dev_fees: vec![coin(30, "unibi"), coin(5, "uusdc")],
+            ..SaleStats::default()
+        }
+    );
+
+    let alice: BuyerStats = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::BuyerStats {
+                    address: "alice".to_string(),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        alice,
+        BuyerStats {
+            bought: 3,
+            spent: vec![coin(220, "unibi"), coin(15, "uusdc")],
+            cw20_spent: vec![],
+        }
+    );
+
+    let nobody: BuyerStats = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::BuyerStats {
+                    address: "nobody".to_string(),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(nobody, BuyerStats::default());
+}
+
+#[test]
+fn updating_config() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let before = contract.config.load(deps.as_ref().storage).unwrap();
+
+    let update = ConfigUpdate {
+        mint_fee: Some(100),
+        dev_fee: Some(10),
+        mint_per_tx: Some(0),
+        ..ConfigUpdate::default()
+    };
+
+    // random cannot
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("random", &[]),
+            ExecuteMsg::UpdateConfig(update.clone()),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+
+    // an invalid field rejects the whole update
+    let owner = mock_info(MINTER, &[]);
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::UpdateConfig(update),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::ZeroMintPerTx {});
+    assert_eq!(before, contract.config.load(deps.as_ref().storage).unwrap());
+
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner,
+            ExecuteMsg::UpdateConfig(ConfigUpdate {
+                mint_fee: Some(100),
+                dev_fee: Some(10),
+                dev_wallet: Some("dev".to_string()),
+                ..ConfigUpdate::default()
+            }),
+        )
+        .unwrap();
+    let config: Config = from_json(
+        contract
+            .query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {})
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        config,
+        Config {
+            mint_fee: 100,
+            dev_fee: 10,
+            dev_wallet: "dev".to_string(),
+            ..before
+        }
+    );
+}
+
+#[test]
+fn accounting_supply_with_burns() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+
+    let owner = mock_info(MINTER, &[]);
+    for msg in [
+        ExecuteMsg::UpdateConfig(ConfigUpdate {
+            supply_limit: Some(2),
+            mint_per_tx: Some(5),
+            ..ConfigUpdate::default()
+        }),
+        ExecuteMsg::Reserve {
+            qty: 2,
+            extension: None,
+            recipient: None,
+        },
+        ExecuteMsg::Burn {
+            token_id: "Magic #1".to_string(),
+        },
+    ] {
+        contract
+            .execute(deps.as_mut(), mock_env(), owner.clone(), msg)
+            .unwrap();
+    }
+
+    let supply_info = |deps: cosmwasm_std::Deps| -> SupplyInfoResponse {
+        from_json(
+            contract
+                .query(deps, mock_env(), QueryMsg::SupplyInfo {})
+                .unwrap(),
+        )
+        .unwrap()
+    };
+
+    // by default burned NFTs keep their slot
+    let expected = SupplyInfoResponse {
+        minted: 2,
+        burned: 1,
+        circulating: 1,
+        used_supply: 2,
+        supply_limit: 2,
+        remaining: 0,
+        reserved: 2,
+        remaining_reserved: None,
+        burns_free_supply: false,
+    };
+    assert_eq!(expected, supply_info(deps.as_ref()));
+    assert_eq!(1, contract.num_tokens(deps.as_ref()).unwrap().count);
+
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::UpdateConfig(ConfigUpdate {
+                burns_free_supply: Some(true),
+                ..ConfigUpdate::default()
+            }),
+        )
+        .unwrap();
+    assert_eq!(
+        SupplyInfoResponse {
+            used_supply: 1,
+            remaining: 1,
+            burns_free_supply: true,
+            ..expected
+        },
+        supply_info(deps.as_ref())
+    );
+
+    // the supply limit can't go below the NFTs counted against it
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner,
+            ExecuteMsg::UpdateConfig(ConfigUpdate {
+                supply_limit: Some(0),
+                ..ConfigUpdate::default()
+            }),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::SupplyLimitTooLow {
+            supply_limit: 0,
+            total_supply: 1
+        }
+    );
+}
+
+#[test]
+fn building_token_ids_from_scheme() {
+    let mut deps = mock_dependencies();
+    let contract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+    let msg = InstantiateMsg {
+        name: CONTRACT_NAME.to_string(),
+        symbol: SYMBOL.to_string(),
+        base_uri: None,
+        token_id_base: Some("magic-".to_string()),
+        minter: Some(String::from(MINTER)),
+        withdraw_address: None,
+        mint_per_tx: Some(10),
+        mint_fee: None,
+        dev_fee: None,
+        supply_limit: None,
+        reserved_amount: None,
+        dev_wallet: None,
+        sale_time: None,
+        history_depth: None,
+        burns_free_supply: None,
+        token_id_scheme: Some(TokenIdScheme::ZeroPadded { width: 21 }),
+        uri_template: None,
+        uri_suffix: None,
+        max_reserved: None,
+        sale_end_time: None,
+        soft_cap: None,
+        purchase_mode: None,
+        max_per_block: None,
+        buy_cooldown: None,
+        operator_filter: None,
+        compliance: None,
+        voucher_signer: None,
+    };
+    // no index has more digits than u64::MAX
+    let err = contract
+        .instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg.clone())
+        .unwrap_err();
+    assert_eq!(err, ContractError::TokenIdWidthTooLarge { width: 21, max: 20 });
+    let msg = InstantiateMsg {
+        token_id_scheme: Some(TokenIdScheme::ZeroPadded { width: 4 }),
+        ..msg
+    };
+    contract
+        .instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg)
+        .unwrap();
+
+    let owner = mock_info(MINTER, &[]);
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::Reserve {
+                qty: 2,
+                extension: None,
+                recipient: None,
+            },
+        )
+        .unwrap();
+    let tokens = contract.all_tokens(deps.as_ref(), None, None, None, None).unwrap();
+    assert_eq!(tokens.tokens, vec!["magic-0001", "magic-0002"]);
+
+    let token_by_index = |deps: cosmwasm_std::Deps, index: u64| {
+        contract
+            .query(deps, mock_env(), QueryMsg::TokenByIndex { index })
+            .map(|res| from_json::<TokenByIndexResponse>(res).unwrap().token_id)
+    };
+    assert_eq!(token_by_index(deps.as_ref(), 2).unwrap(), "magic-0002");
+    let token = contract.tokens.load(deps.as_ref().storage, "magic-0002").unwrap();
+    assert_eq!(token.index, Some(2));
+
+    // burned tokens can't be looked up anymore
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner,
+            ExecuteMsg::Burn {
+                token_id: "magic-0001".to_string(),
+            },
+        )
+        .unwrap();
+    token_by_index(deps.as_ref(), 1).unwrap_err();
+}
+
+#[test]
+fn rendering_token_uris() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+
+    let owner = mock_info(MINTER, &[]);
+    let reserve = ExecuteMsg::Reserve {
+        qty: 1,
+        extension: None,
+        recipient: None,
+    };
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::UpdateConfig(ConfigUpdate {
+                base_uri: Some("ipfs://folder/".to_string()),
+                uri_suffix: Some(".json".to_string()),
+                ..ConfigUpdate::default()
+            }),
+        )
+        .unwrap();
+    contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), reserve.clone())
+        .unwrap();
+    let info = contract
+        .nft_info(deps.as_ref(), "Magic #1".to_string())
+        .unwrap();
+    assert_eq!(Some("ipfs://folder/1.json".to_string()), info.token_uri);
+
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::UpdateConfig(ConfigUpdate {
+                uri_template: Some("{base_uri}{token_id}/meta".to_string()),
+                ..ConfigUpdate::default()
+            }),
+        )
+        .unwrap();
+    contract
+        .execute(deps.as_mut(), mock_env(), owner, reserve)
+        .unwrap();
+    let info = contract
+        .nft_info(deps.as_ref(), "Magic #2".to_string())
+        .unwrap();
+    assert_eq!(
+        Some("ipfs://folder/Magic #2/meta".to_string()),
+        info.token_uri
+    );
+}
+
+#[test]
+fn capping_reserve_allocation() {
+    let mut deps = mock_dependencies();
+    let contract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+    let msg = InstantiateMsg {
+        name: CONTRACT_NAME.to_string(),
+        symbol: SYMBOL.to_string(),
+        base_uri: None,
+        token_id_base: Some(TOKEN_ID_BASE.to_string()),
+        minter: Some(String::from(MINTER)),
+        withdraw_address: None,
+        mint_per_tx: Some(3),
+        mint_fee: None,
+        dev_fee: None,
+        supply_limit: Some(10),
+        reserved_amount: None,
+        dev_wallet: None,
+        sale_time: None,
+        history_depth: None,
+        burns_free_supply: None,
+        token_id_scheme: None,
+        uri_template: None,
+        uri_suffix: None,
+        max_reserved: Some(4),
+        sale_end_time: None,
+        soft_cap: None,
+        purchase_mode: None,
+        max_per_block: None,
+        buy_cooldown: None,
+        operator_filter: None,
+        compliance: None,
+        voucher_signer: None,
+    };
+    contract
+        .instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg)
+        .unwrap();
+
+    // only mint_per_tx are minted, to the recipient
+    let owner = mock_info(MINTER, &[]);
+    let res = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::Reserve {
+                qty: 5,
+                extension: None,
+                recipient: Some("artist".to_string()),
+            },
+        )
+        .unwrap();
+    assert!(res.attributes.iter().any(|a| a.key == "new_reserved" && a.value == "3"));
+    assert_eq!(contract.token_count(&deps.storage).unwrap(), 3);
+    let owner_of: OwnerOfResponse = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::OwnerOf {
+                    token_id: format!("{} #3", TOKEN_ID_BASE),
+                    include_expired: None,
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(owner_of.owner, "artist");
+
+    // the rest of the allocation goes to the owner
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::Reserve {
+                qty: 3,
+                extension: None,
+                recipient: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(contract.token_count(&deps.storage).unwrap(), 4);
+
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner,
+            ExecuteMsg::Reserve {
+                qty: 1,
+                extension: None,
+                recipient: None,
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::ReserveExhausted {});
+
+    let reserved: ReservedAmountResponse = from_json(
+        contract
+            .query(deps.as_ref(), mock_env(), QueryMsg::GetReservedAmount {})
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        reserved,
+        ReservedAmountResponse {
+            reserved: 4,
+            max_reserved: Some(4),
+            remaining: Some(0),
+        }
+    );
+    let reserved_for: u64 = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetReservedFor {
+                    address: "artist".to_string(),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(reserved_for, 3);
+
+    let supply: SupplyInfoResponse = from_json(
+        contract
+            .query(deps.as_ref(), mock_env(), QueryMsg::SupplyInfo {})
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(supply.remaining_reserved, Some(0));
+}
+
+#[test]
+fn scheduling_sale() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let owner = mock_info(MINTER, &[]);
+    let buyer = mock_info("buyer", &[coin(100, "unibi")]);
+    let buy_msg = ExecuteMsg::Buy {
+        qty: 1,
+        extension: None,
+    };
+    let env_at = |seconds: u64| {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(seconds);
+        env
+    };
+    let status_at = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, seconds: u64| {
+        let res: SaleStatusResponse = from_json(
+            contract
+                .query(deps.as_ref(), env_at(seconds), QueryMsg::GetSaleStatus {})
+                .unwrap(),
+        )
+        .unwrap();
+        res
+    };
+    let start = mock_env().block.time.seconds() + 100;
+
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::SetSaleTime { sale_time: start },
+        )
+        .unwrap();
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::SetSaleEndTime {
+                sale_end_time: Some(start - 50),
+            },
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidSaleWindow {
+            sale_time: start,
+            sale_end_time: start - 50,
+        }
+    );
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::SetSaleEndTime {
+                sale_end_time: Some(start + 100),
+            },
+        )
+        .unwrap();
+
+    assert_eq!(
+        status_at(&deps, 0),
+        SaleStatusResponse {
+            status: SaleStatus::NotStarted,
+            sale_time: start,
+            sale_end_time: Some(start + 100),
+        }
+    );
+    assert_eq!(status_at(&deps, 100).status, SaleStatus::Active);
+    contract
+        .execute(deps.as_mut(), env_at(100), buyer.clone(), buy_msg.clone())
+        .unwrap();
+
+    // pausing keeps the schedule
+    contract
+        .execute(deps.as_mut(), env_at(100), owner.clone(), ExecuteMsg::ToggleSaleActive {})
+        .unwrap();
+    assert_eq!(status_at(&deps, 150).status, SaleStatus::Paused);
+    assert_eq!(status_at(&deps, 150).sale_time, start);
+    let err = contract
+        .execute(deps.as_mut(), env_at(150), buyer.clone(), buy_msg.clone())
+        .unwrap_err();
+    assert_eq!(err, ContractError::SaleUnactivate {});
+
+    contract
+        .execute(deps.as_mut(), env_at(150), owner.clone(), ExecuteMsg::ToggleSaleActive {})
+        .unwrap();
+    assert_eq!(status_at(&deps, 150).status, SaleStatus::Active);
+    assert_eq!(status_at(&deps, 200).status, SaleStatus::Ended);
+    let err = contract
+        .execute(deps.as_mut(), env_at(200), buyer.clone(), buy_msg.clone())
+        .unwrap_err();
+    assert_eq!(err, ContractError::SaleEnded {});
+
+    // the sale closes once the supply limit is reached
+    contract
+        .execute(
+            deps.as_mut(),
+            env_at(150),
+            owner,
+            ExecuteMsg::SetSupplyLimit { supply_limit: 1 },
+        )
+        .unwrap();
+    assert_eq!(status_at(&deps, 150).status, SaleStatus::SoldOut);
+    let err = contract
+        .execute(deps.as_mut(), env_at(150), buyer, buy_msg)
+        .unwrap_err();
+    assert_eq!(err, ContractError::SoldOut {});
+}
+
+#[test]
+fn escrowing_until_soft_cap() {
+    let instantiate = |deps: DepsMut<'_>, soft_cap: u64| {
+        let contract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+        let msg = InstantiateMsg {
+            name: CONTRACT_NAME.to_string(),
+            symbol: SYMBOL.to_string(),
+            base_uri: None,
+            token_id_base: Some(TOKEN_ID_BASE.to_string()),
+            minter: Some(String::from(MINTER)),
+            withdraw_address: Some("withdraw".to_string()),
+            mint_per_tx: Some(5),
+            mint_fee: Some(100),
+            dev_fee: Some(10),
+            supply_limit: None,
+            reserved_amount: None,
+            dev_wallet: Some("dev".to_string()),
+            sale_time: Some(0),
+            history_depth: None,
+            burns_free_supply: None,
+            token_id_scheme: None,
+            uri_template: None,
+            uri_suffix: None,
+            max_reserved: None,
+            sale_end_time: Some(mock_env().block.time.seconds() + 100),
+            soft_cap: Some(soft_cap),
+            purchase_mode: None,
+            max_per_block: None,
+            buy_cooldown: None,
+            operator_filter: None,
+            compliance: None,
+            voucher_signer: None,
+        };
+        contract
+            .instantiate(deps, mock_env(), mock_info("creator", &[]), msg)
+            .unwrap();
+        contract
+    };
+    let buy_msg = ExecuteMsg::Buy {
+        qty: 1,
+        extension: None,
+    };
+    let mut ended = mock_env();
+    ended.block.time = ended.block.time.plus_seconds(100);
+
+    // refunds need a sale end time
+    let mut deps = mock_dependencies();
+    let contract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+    let msg = InstantiateMsg {
+        name: CONTRACT_NAME.to_string(),
+        symbol: SYMBOL.to_string(),
+        base_uri: None,
+        token_id_base: None,
+        minter: None,
+        withdraw_address: None,
+        mint_per_tx: None,
+        mint_fee: None,
+        dev_fee: None,
+        supply_limit: None,
+        reserved_amount: None,
+        dev_wallet: None,
+        sale_time: None,
+        history_depth: None,
+        burns_free_supply: None,
+        token_id_scheme: None,
+        uri_template: None,
+        uri_suffix: None,
+        max_reserved: None,
+        sale_end_time: None,
+        soft_cap: Some(3),
+        purchase_mode: None,
+        max_per_block: None,
+        buy_cooldown: None,
+        operator_filter: None,
+        compliance: None,
+        voucher_signer: None,
+    };
+    let err = contract
+        .instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg)
+        .unwrap_err();
+    assert_eq!(err, ContractError::SoftCapWithoutEndTime {});
+
+    // the drop fails, buyers get refunded
+    let mut deps = mock_dependencies();
+    let contract = instantiate(deps.as_mut(), 5);
+    let buyer = mock_info("buyer", &[coin(110, "unibi")]);
+    let res = contract
+        .execute(deps.as_mut(), mock_env(), buyer.clone(), buy_msg.clone())
+        .unwrap();
+    assert!(res.messages.is_empty());
+    let escrow: Escrow = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::Escrow {
+                    address: "buyer".to_string(),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        escrow,
+        Escrow {
+            token_ids: vec![format!("{} #1", TOKEN_ID_BASE)],
+            payments: vec![EscrowedPayment {
+                denom: Denom::Native("unibi".to_string()),
+                mint_fee: Uint128::new(100),
+                dev_fee: Uint128::new(10),
+            }],
+        }
+    );
+
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), mock_info(MINTER, &[]), ExecuteMsg::ReleaseFunds {})
+        .unwrap_err();
+    assert_eq!(err, ContractError::SoftCapNotReached { sold: 1, soft_cap: 5 });
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), buyer.clone(), ExecuteMsg::ClaimRefund {})
+        .unwrap_err();
+    assert_eq!(err, ContractError::RefundUnavailable {});
+
+    // the owner can't postpone refunds
+    let end_time = mock_env().block.time.seconds() + 100;
+    for sale_end_time in [None, Some(end_time + 1)] {
+        let err = contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(MINTER, &[]),
+                ExecuteMsg::SetSaleEndTime { sale_end_time },
+            )
+            .unwrap_err();
+        assert_eq!(err, ContractError::EscrowEndTimeLocked {});
+    }
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::UpdateConfig(ConfigUpdate {
+                sale_end_time: Some(end_time + 1),
+                ..ConfigUpdate::default()
+            }),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::EscrowEndTimeLocked {});
+
+    let other_buyer = mock_info("other_buyer", &[coin(110, "unibi")]);
+    contract
+        .execute(deps.as_mut(), mock_env(), other_buyer.clone(), buy_msg.clone())
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            other_buyer.clone(),
+            ExecuteMsg::TransferNft {
+                recipient: "someone".to_string(),
+                token_id: format!("{} #2", TOKEN_ID_BASE),
+            },
+        )
+        .unwrap();
+
+    let res = contract
+        .execute(deps.as_mut(), ended.clone(), buyer.clone(), ExecuteMsg::ClaimRefund {})
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "buyer".to_string(),
+            amount: vec![coin(110, "unibi")],
+        })]
+    );
+    assert_eq!(contract.token_count(&deps.storage).unwrap(), 1);
+    let stats: SaleStats = from_json(
+        contract
+            .query(deps.as_ref(), mock_env(), QueryMsg::SaleStats {})
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(stats.buyers, 1);
+    assert_eq!(stats.sold, 1);
+    assert_eq!(stats.revenue, vec![coin(110, "unibi")]);
+    let buyer_stats: BuyerStats = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::BuyerStats {
+                    address: "buyer".to_string(),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(buyer_stats, BuyerStats::default());
+    let err = contract
+        .execute(deps.as_mut(), ended.clone(), buyer, ExecuteMsg::ClaimRefund {})
+        .unwrap_err();
+    assert_eq!(err, ContractError::NothingToRefund {});
+    let err = contract
+        .execute(deps.as_mut(), ended.clone(), other_buyer, ExecuteMsg::ClaimRefund {})
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::EscrowedTokenMoved {
+            token_id: format!("{} #2", TOKEN_ID_BASE),
+        }
+    );
+
+    // only the NFTs still held are burned and refunded
+    let third_buyer = mock_info("third_buyer", &[coin(220, "unibi")]);
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            third_buyer.clone(),
+            ExecuteMsg::Buy {
+                qty: 2,
+                extension: None,
+            },
+        )
+        .unwrap();
+    let escrow = contract.escrows.load(&deps.storage, &Addr::unchecked("third_buyer")).unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            third_buyer.clone(),
+            ExecuteMsg::TransferNft {
+                recipient: "someone".to_string(),
+                token_id: escrow.token_ids[1].clone(),
+            },
+        )
+        .unwrap();
+    let res = contract
+        .execute(deps.as_mut(), ended.clone(), third_buyer, ExecuteMsg::ClaimRefund {})
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "third_buyer".to_string(),
+            amount: vec![coin(110, "unibi")],
+        })]
+    );
+    assert!(contract.tokens.may_load(&deps.storage, &escrow.token_ids[0]).unwrap().is_none());
+    assert_eq!(
+        contract.escrows.load(&deps.storage, &Addr::unchecked("third_buyer")).unwrap(),
+        Escrow {
+            token_ids: vec![escrow.token_ids[1].clone()],
+            payments: vec![EscrowedPayment {
+                denom: Denom::Native("unibi".to_string()),
+                mint_fee: Uint128::new(100),
+                dev_fee: Uint128::new(10),
+            }],
+        }
+    );
+    assert_eq!(
+        contract.escrowed.load(&deps.storage).unwrap(),
+        vec![EscrowedPayment {
+            denom: Denom::Native("unibi".to_string()),
+            mint_fee: Uint128::new(200),
+            dev_fee: Uint128::new(20),
+        }]
+    );
+
+    // the soft cap is reached, the owner releases the proceeds
+    let mut deps = mock_dependencies();
+    let contract = instantiate(deps.as_mut(), 2);
+    let buyer = mock_info("buyer", &[coin(220, "unibi")]);
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            buyer.clone(),
+            ExecuteMsg::Buy {
+                qty: 2,
+                extension: None,
+            },
+        )
+        .unwrap();
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), buyer.clone(), ExecuteMsg::ReleaseFunds {})
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+    let res = contract
+        .execute(deps.as_mut(), mock_env(), mock_info(MINTER, &[]), ExecuteMsg::ReleaseFunds {})
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(BankMsg::Send {
+                to_address: "withdraw".to_string(),
+                amount: vec![coin(200, "unibi")],
+            }),
+            SubMsg::new(BankMsg::Send {
+                to_address: "dev".to_string(),
+                amount: vec![coin(20, "unibi")],
+            }),
+        ]
+    );
+    let info: SoftCapInfoResponse = from_json(
+        contract
+            .query(deps.as_ref(), mock_env(), QueryMsg::SoftCapInfo {})
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        info,
+        SoftCapInfoResponse {
+            soft_cap: Some(2),
+            sold: 2,
+            funds_released: true,
+            escrowed: vec![],
+        }
+    );
+
+    // later sales are paid out right away
+    let res = contract
+        .execute(deps.as_mut(), mock_env(), mock_info("buyer", &[coin(110, "unibi")]), buy_msg)
+        .unwrap();
+    assert_eq!(res.messages.len(), 2);
+    let err = contract
+        .execute(deps.as_mut(), ended, buyer, ExecuteMsg::ClaimRefund {})
+        .unwrap_err();
+    assert_eq!(err, ContractError::NoEscrow {});
+}
+
+#[test]
+fn buying_with_commit_reveal() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let owner = mock_info(MINTER, &[]);
+    let env_at = |blocks: u64| {
+        let mut env = mock_env();
+        env.block.height += blocks;
+        env
+    };
+    contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), ExecuteMsg::ToggleSaleActive {})
+        .unwrap();
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::UpdateConfig(ConfigUpdate {
+                purchase_mode: Some(PurchaseMode::CommitReveal {
+                    reveal_delay: 0,
+                    reveal_timeout: 10,
+                }),
+                ..ConfigUpdate::default()
+            }),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::InvalidRevealWindow {});
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::UpdateConfig(ConfigUpdate {
+                purchase_mode: Some(PurchaseMode::CommitReveal {
+                    reveal_delay: 2,
+                    reveal_timeout: 10,
+                }),
+                mint_fee: Some(100),
+                supply_limit: Some(5),
+                ..ConfigUpdate::default()
+            }),
+        )
+        .unwrap();
+
+    let buyer = mock_info("buyer", &[coin(250, "unibi")]);
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            buyer.clone(),
+            ExecuteMsg::Buy {
+                qty: 2,
+                extension: None,
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::DirectBuyDisabled {});
+
+    let salt = Binary::from(b"pepper".as_slice());
+    let commit_msg = ExecuteMsg::CommitBuy {
+        hash: Commitment::hash(&Addr::unchecked("buyer"), 2, &salt),
+    };
+    contract
+        .execute(deps.as_mut(), mock_env(), buyer.clone(), commit_msg.clone())
+        .unwrap();
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), buyer.clone(), commit_msg)
+        .unwrap_err();
+    assert_eq!(err, ContractError::CommitmentPending {});
+
+    let reveal_msg = |qty: u64| ExecuteMsg::RevealBuy {
+        qty,
+        salt: salt.clone(),
+        extension: None,
+    };
+    let err = contract
+        .execute(deps.as_mut(), env_at(1), buyer.clone(), reveal_msg(2))
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::RevealTooEarly {
+            reveal_from: mock_env().block.height + 2,
+        }
+    );
+    let err = contract
+        .execute(deps.as_mut(), env_at(2), buyer.clone(), reveal_msg(1))
+        .unwrap_err();
+    assert_eq!(err, ContractError::InvalidReveal {});
+
+    // nothing is revealed while the sale is paused
+    contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), ExecuteMsg::ToggleSaleActive {})
+        .unwrap();
+    let err = contract
+        .execute(deps.as_mut(), env_at(2), buyer.clone(), reveal_msg(2))
+        .unwrap_err();
+    assert_eq!(err, ContractError::SaleUnactivate {});
+    contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), ExecuteMsg::ToggleSaleActive {})
+        .unwrap();
+    let res = contract
+        .execute(deps.as_mut(), env_at(2), buyer.clone(), reveal_msg(2))
+        .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(BankMsg::Send {
+            to_address: "buyer".to_string(),
+            amount: vec![coin(50, "unibi")],
+        })
+    );
+    assert_eq!(contract.balance(&deps.storage, &Addr::unchecked("buyer")).unwrap(), 2);
+
+    // the rest of the supply is reserved in order around the drawn NFTs
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner,
+            ExecuteMsg::Reserve {
+                qty: 3,
+                extension: None,
+                recipient: None,
+            },
+        )
+        .unwrap();
+    for index in 1..=5 {
+        contract.token_by_index(deps.as_ref(), index).unwrap();
+    }
+    assert_eq!(contract.token_count(&deps.storage).unwrap(), 5);
+
+    // an unrevealed commitment is refunded once expired
+    let other = mock_info("other", &[coin(100, "unibi")]);
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::SetSupplyLimit { supply_limit: 6 },
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            other.clone(),
+            ExecuteMsg::CommitBuy {
+                hash: Commitment::hash(&Addr::unchecked("other"), 1, &salt),
+            },
+        )
+        .unwrap();
+    let err = contract
+        .execute(deps.as_mut(), env_at(10), other.clone(), reveal_msg(1))
+        .unwrap_err();
+    assert_eq!(err, ContractError::CommitmentExpired {});
+    let err = contract
+        .execute(deps.as_mut(), env_at(9), other.clone(), ExecuteMsg::RefundCommitment {})
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::CommitmentNotExpired {
+            expires: mock_env().block.height + 10,
+        }
+    );
+    let res = contract
+        .execute(deps.as_mut(), env_at(10), other, ExecuteMsg::RefundCommitment {})
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "other".to_string(),
+            amount: vec![coin(100, "unibi")],
+        })]
+    );
+    let commitment: Option<Commitment> = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::Commitment {
+                    address: "other".to_string(),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(commitment, None);
+
+    // the draw depends on the block the commitment is revealed in
+    let owner = mock_info(MINTER, &[]);
+    let draw = |reveal_at: u64| {
+        let mut deps = mock_dependencies();
+        let contract = setup_contract(deps.as_mut());
+        contract
+            .execute(deps.as_mut(), mock_env(), owner.clone(), ExecuteMsg::ToggleSaleActive {})
+            .unwrap();
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                owner.clone(),
+                ExecuteMsg::UpdateConfig(ConfigUpdate {
+                    purchase_mode: Some(PurchaseMode::CommitReveal {
+                        reveal_delay: 2,
+                        reveal_timeout: 10,
+                    }),
+                    ..ConfigUpdate::default()
+                }),
+            )
+            .unwrap();
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                buyer.clone(),
+                ExecuteMsg::CommitBuy {
+                    hash: Commitment::hash(&Addr::unchecked("buyer"), 2, &salt),
+                },
+            )
+            .unwrap();
+        contract
+            .execute(deps.as_mut(), env_at(reveal_at), buyer.clone(), reveal_msg(2))
+            .unwrap()
+            .attributes
+    };
+    assert_ne!(draw(2), draw(7));
+}
+
+#[test]
+fn rate_limiting_purchases() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let owner = mock_info(MINTER, &[]);
+    contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), ExecuteMsg::ToggleSaleActive {})
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner,
+            ExecuteMsg::UpdateConfig(ConfigUpdate {
+                max_per_block: Some(3),
+                buy_cooldown: Some(60),
+                ..ConfigUpdate::default()
+            }),
+        )
+        .unwrap();
+    let buy = |qty: u64| ExecuteMsg::Buy {
+        qty,
+        extension: None,
+    };
+    let later = |blocks: u64, seconds: u64| {
+        let mut env = mock_env();
+        env.block.height += blocks;
+        env.block.time = env.block.time.plus_seconds(seconds);
+        env
+    };
+
+    let alice = mock_info("alice", &[coin(100, "unibi")]);
+    contract
+        .execute(deps.as_mut(), mock_env(), alice.clone(), buy(2))
+        .unwrap();
+    let err = contract
+        .execute(deps.as_mut(), later(1, 5), alice.clone(), buy(1))
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::BuyCooldown {
+            next_buy_time: mock_env().block.time.seconds() + 60,
+        }
+    );
+
+    // only one NFT is left in this block
+    let bob = mock_info("bob", &[coin(100, "unibi")]);
+    contract
+        .execute(deps.as_mut(), mock_env(), bob, buy(2))
+        .unwrap();
+    assert_eq!(contract.balance(&deps.storage, &Addr::unchecked("bob")).unwrap(), 1);
+    let carol = mock_info("carol", &[coin(100, "unibi")]);
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), carol.clone(), buy(1))
+        .unwrap_err();
+    assert_eq!(err, ContractError::BlockMintLimit { max_per_block: 3 });
+
+    contract
+        .execute(deps.as_mut(), later(1, 5), carol, buy(1))
+        .unwrap();
+    contract
+        .execute(deps.as_mut(), later(2, 60), alice, buy(1))
+        .unwrap();
+    assert_eq!(contract.token_count(&deps.storage).unwrap(), 5);
+
+    let states: StatesResponse = from_json(
+        contract
+            .query(deps.as_ref(), mock_env(), QueryMsg::GetStates {})
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(states.max_per_block, Some(3));
+    assert_eq!(states.buy_cooldown, Some(60));
+}
+
+#[test]
+fn filtering_operators() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let owner = mock_info(MINTER, &[]);
+    let alice = mock_info("alice", &[]);
+    let token_id = format!("{} #1", TOKEN_ID_BASE);
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::Reserve {
+                qty: 2,
+                extension: None,
+                recipient: Some("alice".to_string()),
+            },
+        )
+        .unwrap();
+    let set_filter = |deps: DepsMut<'_>, filter: OperatorFilter| {
+        contract
+            .execute(
+                deps,
+                mock_env(),
+                owner.clone(),
+                ExecuteMsg::UpdateConfig(ConfigUpdate {
+                    operator_filter: Some(filter),
+                    ..ConfigUpdate::default()
+                }),
+            )
+            .unwrap();
+    };
+
+    // listing the market is up to the owner, approvals are unaffected until the filter is on
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            alice.clone(),
+            ExecuteMsg::UpdateOperatorFilter {
+                add: vec!["market".to_string()],
+                remove: vec![],
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::UpdateOperatorFilter {
+                add: vec!["market".to_string()],
+                remove: vec![],
+            },
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            alice.clone(),
+            ExecuteMsg::ApproveAll {
+                operator: "market".to_string(),
+                expires: None,
+            },
+        )
+        .unwrap();
+
+    set_filter(deps.as_mut(), OperatorFilter::Deny);
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            alice.clone(),
+            ExecuteMsg::Approve {
+                spender: "market".to_string(),
+                token_id: token_id.clone(),
+                expires: None,
+            },
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::OperatorNotAllowed {
+            operator: "market".to_string(),
+        }
+    );
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            alice.clone(),
+            ExecuteMsg::ApproveAll {
+                operator: "market".to_string(),
+                expires: None,
+            },
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::OperatorNotAllowed {
+            operator: "market".to_string(),
+        }
+    );
+    // the existing approval can no longer be used
+    let transfer_msg = ExecuteMsg::TransferNft {
+        recipient: "bob".to_string(),
+        token_id: token_id.clone(),
+    };
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), mock_info("market", &[]), transfer_msg.clone())
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::OperatorNotAllowed {
+            operator: "market".to_string(),
+        }
+    );
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            alice.clone(),
+            ExecuteMsg::Approve {
+                spender: "friend".to_string(),
+                token_id: token_id.clone(),
+                expires: None,
+            },
+        )
+        .unwrap();
+
+    // only the listed market is allowed
+    set_filter(deps.as_mut(), OperatorFilter::Allow);
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), mock_info("friend", &[]), transfer_msg.clone())
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::OperatorNotAllowed {
+            operator: "friend".to_string(),
+        }
+    );
+    contract
+        .execute(deps.as_mut(), mock_env(), mock_info("market", &[]), transfer_msg)
+        .unwrap();
+
+    let allowed: bool = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::OperatorAllowed {
+                    operator: "friend".to_string(),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert!(!allowed);
+    let filter: OperatorFilterResponse = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::OperatorFilter {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        filter,
+        OperatorFilterResponse {
+            filter: OperatorFilter::Allow,
+            operators: vec!["market".to_string()],
+        }
+    );
+
+    // owners are never filtered, and the filter can be turned off
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            alice.clone(),
+            ExecuteMsg::TransferNft {
+                recipient: "bob".to_string(),
+                token_id: format!("{} #2", TOKEN_ID_BASE),
+            },
+        )
+        .unwrap();
+    set_filter(deps.as_mut(), OperatorFilter::Off);
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &[]),
+            ExecuteMsg::ApproveAll {
+                operator: "friend".to_string(),
+                expires: None,
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+fn blocklisting_addresses() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let owner = mock_info(MINTER, &[]);
+    let officer = mock_info("officer", &[]);
+    let sanctioned = mock_info("sanctioned", &[coin(100, "unibi")]);
+    contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), ExecuteMsg::ToggleSaleActive {})
+        .unwrap();
+    let block_msg = ExecuteMsg::AddToBlocklist {
+        addresses: vec!["sanctioned".to_string(), "bad".to_string()],
+    };
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), officer.clone(), block_msg.clone())
+        .unwrap_err();
+    assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::SetCompliance {
+                address: Some("officer".to_string()),
+            },
+        )
+        .unwrap();
+    contract
+        .execute(deps.as_mut(), mock_env(), officer.clone(), block_msg)
+        .unwrap();
+
+    let blocklisted = |address: &str| ContractError::Blocklisted {
+        address: address.to_string(),
+    };
+    let buy_msg = ExecuteMsg::Buy {
+        qty: 1,
+        extension: None,
+    };
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), sanctioned.clone(), buy_msg.clone())
+        .unwrap_err();
+    assert_eq!(err, blocklisted("sanctioned"));
+    let reserve_msg = |recipient: &str| ExecuteMsg::Reserve {
+        qty: 1,
+        extension: None,
+        recipient: Some(recipient.to_string()),
+    };
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), reserve_msg("bad"))
+        .unwrap_err();
+    assert_eq!(err, blocklisted("bad"));
+    contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), reserve_msg("alice"))
+        .unwrap();
+
+    let token_id = format!("{} #1", TOKEN_ID_BASE);
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::TransferNft {
+                recipient: "sanctioned".to_string(),
+                token_id: token_id.clone(),
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, blocklisted("sanctioned"));
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::SendNft {
+                contract: "bad".to_string(),
+                token_id,
+                msg: to_json_binary("hello").unwrap(),
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, blocklisted("bad"));
+
+    let blocklist: BlocklistResponse = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::Blocklist {
+                    start_after: Some("bad".to_string()),
+                    limit: Some(1),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(blocklist.addresses, vec!["sanctioned".to_string()]);
+
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            officer.clone(),
+            ExecuteMsg::RemoveFromBlocklist {
+                addresses: vec!["sanctioned".to_string()],
+            },
+        )
+        .unwrap();
+    contract
+        .execute(deps.as_mut(), mock_env(), sanctioned, buy_msg)
+        .unwrap();
+
+    // NFTs held before being blocklisted can't be redeemed
+    contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), reserve_msg("carl"))
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner,
+            ExecuteMsg::AddRecipe {
+                inputs: vec![RecipeInput {
+                    count: 1,
+                    extension: None,
+                }],
+                output_uri: None,
+                output_extension: None,
+                supply_cap: None,
+                count_in_supply: false,
+            },
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            officer,
+            ExecuteMsg::AddToBlocklist {
+                addresses: vec!["carl".to_string()],
+            },
+        )
+        .unwrap();
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("carl", &[]),
+            ExecuteMsg::Redeem {
+                recipe_id: 1,
+                token_ids: vec![format!("{} #3", TOKEN_ID_BASE)],
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, blocklisted("carl"));
+}
+
+#[test]
+fn pruning_expired_approvals() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let owner = mock_info(MINTER, &[]);
+    let alice = mock_info("alice", &[]);
+    let token_id = format!("{} #1", TOKEN_ID_BASE);
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner,
+            ExecuteMsg::Reserve {
+                qty: 1,
+                extension: None,
+                recipient: Some("alice".to_string()),
+            },
+        )
+        .unwrap();
+
+    let expires = Expiration::AtHeight(mock_env().block.height + 10);
+    for spender in ["bob", "carl"] {
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                alice.clone(),
+                ExecuteMsg::Approve {
+                    spender: spender.to_string(),
+                    token_id: token_id.clone(),
+                    expires: Some(expires),
+                },
+            )
+            .unwrap();
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                alice.clone(),
+                ExecuteMsg::ApproveAll {
+                    operator: spender.to_string(),
+                    expires: Some(expires),
+                },
+            )
+            .unwrap();
+    }
+
+    let mut later = mock_env();
+    later.block.height += 20;
+
+    // approving again drops the stale entries along the way
+    contract
+        .execute(
+            deps.as_mut(),
+            later.clone(),
+            alice.clone(),
+            ExecuteMsg::Approve {
+                spender: "bob".to_string(),
+                token_id: token_id.clone(),
+                expires: None,
+            },
+        )
+        .unwrap();
+    let token = contract.tokens.load(&deps.storage, &token_id).unwrap();
+    assert_eq!(token.approvals.len(), 1);
+    assert_eq!(token.approvals[0].spender, Addr::unchecked("bob"));
+
+    // anyone can prune, the limit bounds the token ids and operators looked at
+    contract
+        .execute(
+            deps.as_mut(),
+            later.clone(),
+            alice.clone(),
+            ExecuteMsg::Approve {
+                spender: "carl".to_string(),
+                token_id: token_id.clone(),
+                expires: Some(Expiration::AtHeight(later.block.height + 1)),
+            },
+        )
+        .unwrap();
+    let mut latest = later.clone();
+    latest.block.height += 5;
+    let res = contract
+        .execute(
+            deps.as_mut(),
+            latest.clone(),
+            mock_info("random", &[]),
+            ExecuteMsg::PruneExpired {
+                token_ids: vec![token_id.clone()],
+                owners: vec!["alice".to_string()],
+                start_after: None,
+                limit: Some(2),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        res.attributes[1..],
+        [
+            ("pruned_approvals", "1"),
+            ("pruned_operators", "1"),
+            ("next_owner", "alice"),
+            ("next_start_after", "bob"),
+        ]
+    );
+    let token = contract.tokens.load(&deps.storage, &token_id).unwrap();
+    assert_eq!(token.approvals.len(), 1);
+    let operators = contract
+        .operators
+        .prefix(&Addr::unchecked("alice"))
+        .keys(&deps.storage, None, None, Order::Ascending)
+        .count();
+    assert_eq!(operators, 1);
+
+    let res = contract
+        .execute(
+            deps.as_mut(),
+            latest,
+            mock_info("random", &[]),
+            ExecuteMsg::PruneExpired {
+                token_ids: vec!["unknown".to_string(), token_id],
+                owners: vec!["alice".to_string()],
+                start_after: Some("bob".to_string()),
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        res.attributes[1..],
+        [("pruned_approvals", "0"), ("pruned_operators", "1")]
+    );
+    let operators = contract
+        .operators
+        .prefix(&Addr::unchecked("alice"))
+        .keys(&deps.storage, None, None, Order::Ascending)
+        .count();
+    assert_eq!(operators, 0);
+}
+
+#[test]
+fn approving_with_permits() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let mut env = mock_env();
+    env.contract.address = Addr::unchecked(
+        bech32::encode("nibi", bech32::ToBase32::to_base32(&[1u8; 32]), bech32::Variant::Bech32)
+            .unwrap(),
+    );
+    let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+    let other_key = SigningKey::from_slice(&[8u8; 32]).unwrap();
+    let pubkey = Binary::from(key.verifying_key().to_encoded_point(true).as_bytes());
+    let owner = pubkey_address("nibi", &pubkey).unwrap();
+    let token_id = format!("{} #1", TOKEN_ID_BASE);
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::Reserve {
+                qty: 1,
+                extension: None,
+                recipient: Some(owner.clone()),
+            },
+        )
+        .unwrap();
+
+    let sign = |key: &SigningKey, spender: &str, token_id: Option<String>, nonce: u64| {
+        let payload = PermitPayload {
+            chain_id: env.block.chain_id.clone(),
+            contract: env.contract.address.to_string(),
+            spender: spender.to_string(),
+            token_id,
+            expires: None,
+            nonce,
+        };
+        let signature: Signature = key.sign_prehash(&payload.hash().unwrap()).unwrap();
+        Binary::from(signature.to_bytes().as_slice())
+    };
+    let permit = |spender: &str, token_id: Option<String>, nonce: u64, signature: Binary| {
+        ExecuteMsg::Permit {
+            owner_pubkey: pubkey.clone(),
+            spender: spender.to_string(),
+            token_id,
+            expires: None,
+            nonce,
+            signature,
+        }
+    };
+    let relayer = mock_info("relayer", &[]);
+
+    // anyone can submit a permit signed by the owner
+    let signature = sign(&key, "market", Some(token_id.clone()), 0);
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            relayer.clone(),
+            permit("market", Some(token_id.clone()), 0, signature.clone()),
+        )
+        .unwrap();
+    let token = contract.tokens.load(&deps.storage, &token_id).unwrap();
+    assert_eq!(token.approvals[0].spender, Addr::unchecked("market"));
+
+    // it can't be replayed
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            relayer.clone(),
+            permit("market", Some(token_id.clone()), 0, signature),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::InvalidNonce { expected: 1 });
+
+    // nor altered, or signed with another key
+    let signature = sign(&key, "market", None, 1);
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            relayer.clone(),
+            permit("thief", None, 1, signature.clone()),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::InvalidPermit {});
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            relayer.clone(),
+            permit("market", None, 1, sign(&other_key, "market", None, 1)),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::InvalidPermit {});
+
+    // permits without token id approve the operator for all tokens
+    contract
+        .execute(
+            deps.as_mut(),
+            env.clone(),
+            relayer,
+            permit("market", None, 1, signature),
+        )
+        .unwrap();
+    let operator = contract
+        .operators
+        .load(&deps.storage, (&Addr::unchecked(&owner), &Addr::unchecked("market")))
+        .unwrap();
+    assert_eq!(operator, Expiration::Never {});
+    let nonce: u64 = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                env,
+                QueryMsg::PermitNonce { owner },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(nonce, 2);
+}
+
+#[test]
+fn buying_with_vouchers() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    let owner = mock_info(MINTER, &[]);
+    let signer = SigningKey::from_slice(&[7u8; 32]).unwrap();
+    let new_signer = SigningKey::from_slice(&[8u8; 32]).unwrap();
+    let pubkey = |key: &SigningKey| Binary::from(key.verifying_key().to_encoded_point(true).as_bytes());
+    let now = mock_env().block.time.seconds();
+    contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), ExecuteMsg::ToggleSaleActive {})
+        .unwrap();
+
+    let voucher = |key: &SigningKey, buyer: &str, qty: u64, expires: u64, nonce: u64| {
+        let price = coin(50, "unibi");
+        let payload = VoucherPayload {
+            chain_id: mock_env().block.chain_id,
+            contract: mock_env().contract.address.to_string(),
+            buyer: buyer.to_string(),
+            max_qty: 3,
+            price: price.clone(),
+            expires,
+            nonce,
+        };
+        let signature: Signature = key.sign_prehash(&payload.hash().unwrap()).unwrap();
+        ExecuteMsg::BuyWithVoucher {
+            qty,
+            max_qty: 3,
+            price,
+            expires,
+            nonce,
+            signature: Binary::from(signature.to_bytes().as_slice()),
+            extension: None,
+        }
+    };
+    let alice = mock_info("alice", &[coin(100, "unibi")]);
+
+    // vouchers are disabled until the owner sets a signer key
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), alice.clone(), voucher(&signer, "alice", 2, now + 100, 1))
+        .unwrap_err();
+    assert_eq!(err, ContractError::NoVoucherSigner {});
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::SetVoucherSigner {
+                pubkey: Some(Binary::from(&[4u8; 33])),
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::InvalidVoucherSigner {});
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::SetVoucherSigner {
+                pubkey: Some(pubkey(&signer)),
+            },
+        )
+        .unwrap();
+
+    // the voucher is bound to its buyer and caps the quantity
+    let err = contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &[coin(100, "unibi")]),
+            voucher(&signer, "alice", 2, now + 100, 1),
+        )
+        .unwrap_err();
+    assert_eq!(err, ContractError::InvalidVoucher {});
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), alice.clone(), voucher(&signer, "alice", 4, now + 100, 1))
+        .unwrap_err();
+    assert_eq!(err, ContractError::VoucherQtyExceeded { max_qty: 3 });
+
+    // the voucher price replaces the configured one, and the voucher can't be reused
+    let res = contract
+        .execute(deps.as_mut(), mock_env(), alice.clone(), voucher(&signer, "alice", 2, now + 100, 1))
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: MINTER.to_string(),
+            amount: vec![coin(100, "unibi")],
+        })]
+    );
+    let used: bool = from_json(
+        contract
+            .query(deps.as_ref(), mock_env(), QueryMsg::VoucherUsed { nonce: 1 })
+            .unwrap(),
+    )
+    .unwrap();
+    assert!(used);
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), alice.clone(), voucher(&signer, "alice", 1, now + 100, 1))
+        .unwrap_err();
+    assert_eq!(err, ContractError::VoucherUsed { nonce: 1 });
+
+    // rotating the signer invalidates the vouchers it signed
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner,
+            ExecuteMsg::SetVoucherSigner {
+                pubkey: Some(pubkey(&new_signer)),
+            },
+        )
+        .unwrap();
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), alice.clone(), voucher(&signer, "alice", 1, now + 100, 2))
+        .unwrap_err();
+    assert_eq!(err, ContractError::InvalidVoucher {});
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), alice.clone(), voucher(&new_signer, "alice", 1, now, 2))
+        .unwrap_err();
+    assert_eq!(err, ContractError::VoucherExpired {});
+    contract
+        .execute(deps.as_mut(), mock_env(), alice, voucher(&new_signer, "alice", 1, now + 100, 2))
+        .unwrap();
+}
+
+#[test]
+fn batching_token_queries() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::Reserve {
+                qty: 3,
+                extension: None,
+                recipient: Some("alice".to_string()),
+            },
+        )
+        .unwrap();
+    let token_ids: Vec<String> = (1..=3).map(|i| format!("{} #{}", TOKEN_ID_BASE, i)).collect();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Approve {
+                spender: "market".to_string(),
+                token_id: token_ids[2].clone(),
+                expires: None,
+            },
+        )
+        .unwrap();
+
+    let res: NftInfoBatchResponse<Extension> = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::NftInfoBatch {
+                    token_ids: vec![token_ids[2].clone(), token_ids[0].clone()],
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    let queried: Vec<_> = res.tokens.iter().map(|token| token.token_id.clone()).collect();
+    assert_eq!(queried, vec![token_ids[2].clone(), token_ids[0].clone()]);
+    assert_eq!(
+        res.tokens[0].info,
+        contract.nft_info(deps.as_ref(), token_ids[2].clone()).unwrap()
+    );
+
+    let res: OwnerOfBatchResponse = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::OwnerOfBatch {
+                    token_ids: token_ids.clone(),
+                    include_expired: None,
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert!(res.tokens.iter().all(|token| token.access.owner == "alice"));
+    assert_eq!(res.tokens[2].access.approvals.len(), 1);
+
+    // missing tokens fail the whole batch, like the single token queries
+    contract
+        .query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::OwnerOfBatch {
+                token_ids: vec!["missing".to_string()],
+                include_expired: None,
+            },
+        )
+        .unwrap_err();
+
+    let res: AllTokensInfoResponse<Extension> = from_json(
+        contract
+            .query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::AllTokensInfo {
+                    start_after: Some(token_ids[0].clone()),
+                    limit: Some(1),
+                },
+            )
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.tokens.len(), 1);
+    assert_eq!(res.tokens[0].token_id, token_ids[1]);
+    assert_eq!(
+        res.tokens[0].info,
+        contract
+            .all_nft_info(deps.as_ref(), mock_env(), token_ids[1].clone(), false)
+            .unwrap()
+    );
+}
+
+#[test]
+fn enumerating_tokens_by_index() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::Reserve {
+                qty: 12,
+                extension: None,
+                recipient: Some("alice".to_string()),
+            },
+        )
+        .unwrap();
+    let token_id = |index: u64| format!("{} #{}", TOKEN_ID_BASE, index);
+    for index in [2, 10] {
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                ExecuteMsg::TransferNft {
+                    recipient: "bob".to_string(),
+                    token_id: token_id(index),
+                },
+            )
+            .unwrap();
+    }
+    let query = |deps: &cosmwasm_std::OwnedDeps<_, _, _>, msg: QueryMsg<Empty>| -> Vec<String> {
+        let res: TokensResponse = from_json(contract.query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        res.tokens
+    };
+
+    // "#10" no longer sorts before "#2"
+    let tokens = query(
+        &deps,
+        QueryMsg::AllTokensByIndex {
+            start_after: None,
+            start_before: None,
+            limit: Some(3),
+            order: None,
+        },
+    );
+    assert_eq!(tokens, vec![token_id(1), token_id(2), token_id(3)]);
+    let tokens = query(
+        &deps,
+        QueryMsg::AllTokensByIndex {
+            start_after: Some(token_id(3)),
+            start_before: None,
+            limit: Some(1),
+            order: Some(OrderBy::Asc),
+        },
+    );
+    assert_eq!(tokens, vec![token_id(4)]);
+    let tokens = query(
+        &deps,
+        QueryMsg::AllTokensByIndex {
+            start_after: None,
+            start_before: Some(token_id(12)),
+            limit: Some(2),
+            order: Some(OrderBy::Desc),
+        },
+    );
+    assert_eq!(tokens, vec![token_id(11), token_id(10)]);
+
+    // the owner index follows transfers
+    let tokens = query(
+        &deps,
+        QueryMsg::TokensByIndex {
+            owner: "bob".to_string(),
+            start_after: None,
+            start_before: None,
+            limit: None,
+            order: None,
+        },
+    );
+    assert_eq!(tokens, vec![token_id(2), token_id(10)]);
+    let tokens = query(
+        &deps,
+        QueryMsg::TokensByIndex {
+            owner: "alice".to_string(),
+            start_after: None,
+            start_before: Some(token_id(11)),
+            limit: Some(3),
+            order: Some(OrderBy::Desc),
+        },
+    );
+    assert_eq!(tokens, vec![token_id(9), token_id(8), token_id(7)]);
+
+    // a page can continue from a token burned in the meantime
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Burn {
+                token_id: token_id(7),
+            },
+        )
+        .unwrap();
+    let tokens = query(
+        &deps,
+        QueryMsg::TokensByIndex {
+            owner: "alice".to_string(),
+            start_after: None,
+            start_before: Some(token_id(7)),
+            limit: Some(2),
+            order: Some(OrderBy::Desc),
+        },
+    );
+    assert_eq!(tokens, vec![token_id(6), token_id(5)]);
+    let tokens = query(
+        &deps,
+        QueryMsg::AllTokensByIndex {
+            start_after: Some(token_id(7)),
+            start_before: None,
+            limit: Some(1),
+            order: None,
+        },
+    );
+    assert_eq!(tokens, vec![token_id(8)]);
+}
+
+#[test]
+fn paginating_in_reverse() {
+    let mut deps = mock_dependencies();
+    let contract = setup_contract(deps.as_mut());
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(MINTER, &[]),
+            ExecuteMsg::Reserve {
+                qty: 4,
+                extension: None,
+                recipient: Some("alice".to_string()),
+            },
+        )
+        .unwrap();
+    let token_id = |index: u64| format!("{} #{}", TOKEN_ID_BASE, index);
+    for operator in ["bob", "carl", "dave"] {
+        contract
+            .execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("alice", &[]),
+                ExecuteMsg::ApproveAll {
+                    operator: operator.to_string(),
+                    expires: None,
+                },
+            )
+            .unwrap();
+    }
+
+    let tokens = contract
+        .all_tokens(deps.as_ref(), None, None, Some(2), Some(OrderBy::Desc))
+        .unwrap();
+    assert_eq!(tokens.tokens, vec![token_id(4), token_id(3)]);
+    let tokens = contract
+        .all_tokens(deps.as_ref(), None, Some(token_id(3)), None, Some(OrderBy::Desc))
+        .unwrap();
+    assert_eq!(tokens.tokens, vec![token_id(2), token_id(1)]);
+
+    // both bounds apply whatever the order
+    let tokens = contract
+        .tokens(
+            deps.as_ref(),
+            "alice".to_string(),
+            Some(token_id(1)),
+            Some(token_id(4)),
+            None,
+            Some(OrderBy::Desc),
+        )
+        .unwrap();
+    assert_eq!(tokens.tokens, vec![token_id(3), token_id(2)]);
+    let tokens = contract
+        .tokens(
+            deps.as_ref(),
+            "alice".to_string(),
+            Some(token_id(1)),
+            Some(token_id(4)),
+            None,
+            None,
+        )
+        .unwrap();
+    assert_eq!(tokens.tokens, vec![token_id(2), token_id(3)]);
+
+    let operators = contract
+        .operators(
+            deps.as_ref(),
+            mock_env(),
+            "alice".to_string(),
+            false,
+            None,
+            Some("dave".to_string()),
+            Some(1),
+            Some(OrderBy::Desc),
+        )
+        .unwrap();
+    assert_eq!(operators.operators.len(), 1);
+    assert_eq!(operators.operators[0].spender, "carl");
+}
+
+#[test]
+fn withdrawing_funds() {
+    let mut deps = mock_dependencies();
+    let contract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+    let msg = InstantiateMsg {
+        name: CONTRACT_NAME.to_string(),
+        symbol: SYMBOL.to_string(),
+        base_uri: None,
+        token_id_base: Some(TOKEN_ID_BASE.to_string()),
+        minter: Some(String::from(MINTER)),
+        withdraw_address: Some("withdraw".to_string()),
+        mint_per_tx: Some(5),
+        mint_fee: Some(100),
+        dev_fee: Some(10),
+        supply_limit: None,
+        reserved_amount: None,
+        dev_wallet: Some("dev".to_string()),
+        sale_time: Some(0),
+        history_depth: None,
+        burns_free_supply: None,
+        token_id_scheme: None,
+        uri_template: None,
+        uri_suffix: None,
+        max_reserved: None,
+        sale_end_time: Some(mock_env().block.time.seconds() + 100),
+        soft_cap: Some(3),
+        purchase_mode: None,
+        max_per_block: None,
+        buy_cooldown: None,
+        operator_filter: None,
+        compliance: None,
+        voucher_signer: None,
+    };
+    contract
+        .instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg)
+        .unwrap();
+    let owner = mock_info(MINTER, &[]);
+    let withdraw = |amount: u128| ExecuteMsg::WithdrawFunds {
+        amount: coin(amount, "unibi"),
+    };
+
+    // the escrowed purchase can't be withdrawn, other funds can
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("buyer", &[coin(110, "unibi")]),
+            ExecuteMsg::Buy {
+                qty: 1,
+                extension: None,
+            },
+        )
+        .unwrap();
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![coin(160, "unibi")]);
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), withdraw(110))
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::FundsLocked {
+            available: Uint128::new(50)
+        }
+    );
+    let res = contract
+        .execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), withdraw(50))
+        .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "withdraw".to_string(),
+            amount: vec![coin(50, "unibi")],
+        })]
+    );
+
+    // payments of pending commitments are held as well
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![coin(110, "unibi")]);
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            owner.clone(),
+            ExecuteMsg::UpdateConfig(ConfigUpdate {
+                purchase_mode: Some(PurchaseMode::CommitReveal {
+                    reveal_delay: 2,
+                    reveal_timeout: 10,
+                }),
+                ..ConfigUpdate::default()
+            }),
+        )
+        .unwrap();
+    contract
+        .execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("committer", &[coin(220, "unibi")]),
+            ExecuteMsg::CommitBuy {
+                hash: Commitment::hash(&Addr::unchecked("committer"), 2, b"salt"),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        contract.committed.load(&deps.storage).unwrap(),
+        vec![coin(220, "unibi")]
+    );
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![coin(340, "unibi")]);
+    let err = contract
+        .execute(deps.as_mut(), mock_env(), owner.clone(), withdraw(11))
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::FundsLocked {
+            available: Uint128::new(10)
+        }
+    );
+    contract
+        .execute(deps.as_mut(), mock_env(), owner, withdraw(10))
+        .unwrap();
+}
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use cw_ownable::OwnershipError;
 use thiserror::Error;
 
@@ -13,6 +13,9 @@ pub enum ContractError {
     #[error(transparent)]
     Version(#[from] cw2::VersionError),
 
+    #[error("Cannot migrate from {from} to older version {to}")]
+    Downgrade { from: String, to: String },
+
     #[error("this function is blocked")]
     Blocked {},
 
@@ -28,9 +31,138 @@ pub enum ContractError {
     #[error("No withdraw address set")]
     NoWithdrawAddress {},
 
+    #[error("Only {available} can be withdrawn, the rest is held for buyers")]
+    FundsLocked { available: Uint128 },
+
     #[error("Sale Unactivate")]
     SaleUnactivate {},
 
+    #[error("Sale has ended")]
+    SaleEnded {},
+
+    #[error("Token id width {width} is over the maximum of {max}")]
+    TokenIdWidthTooLarge { width: u32, max: u32 },
+
+    #[error("Sale end time {sale_end_time} must be after the sale time {sale_time}")]
+    InvalidSaleWindow { sale_time: u64, sale_end_time: u64 },
+
     #[error("Incorrect Funds")]
     IncorrectFunds {},
+
+    #[error("mint_per_tx must be greater than zero")]
+    ZeroMintPerTx {},
+
+    #[error("Supply limit {supply_limit} is lower than the total supply {total_supply}")]
+    SupplyLimitTooLow { supply_limit: u64, total_supply: u64 },
+
+    #[error("Supply limit reached")]
+    SoldOut {},
+
+    #[error("Reserve allocation exhausted")]
+    ReserveExhausted {},
+
+    #[error("Address {address} is blocklisted")]
+    Blocklisted { address: String },
+
+    #[error("Vouchers are disabled")]
+    NoVoucherSigner {},
+
+    #[error("Voucher signer must be a compressed secp256k1 public key")]
+    InvalidVoucherSigner {},
+
+    #[error("Voucher signature is invalid")]
+    InvalidVoucher {},
+
+    #[error("Voucher has expired")]
+    VoucherExpired {},
+
+    #[error("Voucher {nonce} was already used")]
+    VoucherUsed { nonce: u64 },
+
+    #[error("Voucher allows at most {max_qty} NFTs")]
+    VoucherQtyExceeded { max_qty: u64 },
+
+    #[error("Permit signature is invalid")]
+    InvalidPermit {},
+
+    #[error("Permit nonce must be {expected}")]
+    InvalidNonce { expected: u64 },
+
+    #[error("Operator {operator} is not allowed by the operator filter")]
+    OperatorNotAllowed { operator: String },
+
+    #[error("At most {max_per_block} NFTs can be bought per block")]
+    BlockMintLimit { max_per_block: u64 },
+
+    #[error("Next purchase allowed at {next_buy_time}")]
+    BuyCooldown { next_buy_time: u64 },
+
+    #[error("Direct buying is disabled, use CommitBuy and RevealBuy")]
+    DirectBuyDisabled {},
+
+    #[error("Commit-reveal buying is disabled, use Buy")]
+    CommitRevealDisabled {},
+
+    #[error("reveal_delay must be greater than zero and lower than reveal_timeout")]
+    InvalidRevealWindow {},
+
+    #[error("A commitment is already pending")]
+    CommitmentPending {},
+
+    #[error("No pending commitment")]
+    NoCommitment {},
+
+    #[error("Commitment can't be revealed before block {reveal_from}")]
+    RevealTooEarly { reveal_from: u64 },
+
+    #[error("Commitment expired, it can only be refunded")]
+    CommitmentExpired {},
+
+    #[error("Commitment can't be refunded before block {expires}")]
+    CommitmentNotExpired { expires: u64 },
+
+    #[error("qty and salt do not match the commitment")]
+    InvalidReveal {},
+
+    #[error("Sale proceeds are not held in escrow")]
+    NoEscrow {},
+
+    #[error("Only {sold} NFTs sold, below the soft cap of {soft_cap}")]
+    SoftCapNotReached { sold: u64, soft_cap: u64 },
+
+    #[error("A sale with a soft cap needs a sale end time")]
+    SoftCapWithoutEndTime {},
+
+    #[error("Sale end time can't be cleared or extended while proceeds are held in escrow")]
+    EscrowEndTimeLocked {},
+
+    #[error("Refunds are only available once the sale has ended below its soft cap")]
+    RefundUnavailable {},
+
+    #[error("Nothing to refund")]
+    NothingToRefund {},
+
+    #[error("Escrowed NFT {token_id} is no longer held by its buyer")]
+    EscrowedTokenMoved { token_id: String },
+
+    #[error("Recipe must have at least one input with a non-zero count")]
+    InvalidRecipe {},
+
+    #[error("Tokens do not match the inputs of recipe {recipe_id}")]
+    RecipeMismatch { recipe_id: u64 },
+
+    #[error("Recipe {recipe_id} has reached its supply cap")]
+    RecipeSoldOut { recipe_id: u64 },
+
+    #[error("Token {token} is not accepted as payment")]
+    UnacceptedToken { token: String },
+
+    #[error("Only one denom can be sent at a time")]
+    MultipleDenoms {},
+
+    #[error("Denom {denom} is not accepted as payment")]
+    UnacceptedDenom { denom: String },
+
+    #[error("The price in {denom} is set through SetMintFee and SetDevFee")]
+    DefaultDenom { denom: String },
 }
@@ -3,17 +3,29 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use cosmwasm_std::{
-    coin, Addr, BankMsg, Binary, Coin, CustomMsg, Deps, DepsMut, Env, MessageInfo, Response,
-    Storage
+    coin, from_json, to_json_binary, to_json_vec, Addr, BankMsg, Binary, Coin, CosmosMsg,
+    BlockInfo, CustomMsg, Deps, DepsMut, Empty, Env, MessageInfo, Order, Response, StdError,
+    StdResult, Storage, Uint128, WasmMsg,
 };
 
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, Denom};
+use cw_storage_plus::Bound;
 use cw721::{ContractInfoResponse, Cw721Execute, Cw721ReceiveMsg, Expiration};
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg};
-use crate::state::{Approval, Cw721Contract, TokenInfo};
+use crate::msg::{ConfigUpdate, ExecuteMsg, InstantiateMsg, ReceiveMsg};
+use crate::state::{
+    pubkey_address, Approval, Commitment, Config, Cw721Contract, Escrow, EscrowedPayment,
+    HistoryAction, HistoryRecord, PermitPayload, Price, PurchaseMode, Recipe, SaleState,
+    SaleStatus, TokenIdScheme, TokenInfo, VoucherPayload, DEFAULT_URI_TEMPLATE, MAX_ID_WIDTH,
+};
+use crate::NATIVE_DENOM;
+use sha2::{Digest, Sha256};
 use std::cmp;
 
+const DEFAULT_PRUNE_LIMIT: u32 = 30;
+const MAX_PRUNE_LIMIT: u32 = 100;
+
 impl<'a, T, C, E, Q> Cw721Contract<'a, T, C, E, Q>
 where
     T: Serialize + DeserializeOwned + Clone,
@@ -33,30 +45,58 @@ where
             symbol: msg.symbol,
         };
         self.contract_info.save(deps.storage, &contract_info)?;
-        let base_uri = msg.base_uri.unwrap_or_else(|| "".into());
-        let token_id_base = msg.token_id_base.unwrap_or_else(|| "".into());
-
-        let mint_per_tx = msg.mint_per_tx.unwrap_or_else(|| 1u64);
-        let mint_fee = msg.mint_fee.unwrap_or_else(|| 0u64);
-        let dev_fee = msg.dev_fee.unwrap_or_else(|| 0u64);
-        let supply_limit = msg.supply_limit.unwrap_or_else(|| 100000u64);
-        let total_supply = 0u64;
-        let reserved_amount = msg.reserved_amount.unwrap_or_else(|| 0u64);
-        let dev_wallet = msg
-            .dev_wallet
-            .unwrap_or_else(|| info.clone().sender.to_string());
-        let sale_time = msg.sale_time.unwrap_or_else(|| std::u64::MAX);
-
-        self.mint_per_tx.save(deps.storage, &mint_per_tx)?;
-        self.mint_fee.save(deps.storage, &mint_fee)?;
-        self.dev_fee.save(deps.storage, &dev_fee)?;
-        self.supply_limit.save(deps.storage, &supply_limit)?;
-        self.total_supply.save(deps.storage, &total_supply)?;
-        self.reserved_amount.save(deps.storage, &reserved_amount)?;
-        self.dev_wallet.save(deps.storage, &dev_wallet)?;
-        self.sale_time.save(deps.storage, &sale_time)?;
-        self.base_uri.save(deps.storage, &base_uri)?;
-        self.token_id_base.save(deps.storage, &token_id_base)?;
+        if let Some(address) = &msg.withdraw_address {
+            deps.api.addr_validate(address)?;
+        }
+        let config = Config {
+            base_uri: msg.base_uri.unwrap_or_default(),
+            uri_template: msg
+                .uri_template
+                .unwrap_or_else(|| DEFAULT_URI_TEMPLATE.to_string()),
+            uri_suffix: msg.uri_suffix.unwrap_or_default(),
+            token_id_base: msg.token_id_base.unwrap_or_default(),
+            token_id_scheme: msg.token_id_scheme.unwrap_or_default(),
+            withdraw_address: msg.withdraw_address,
+            dev_wallet: msg
+                .dev_wallet
+                .unwrap_or_else(|| info.sender.to_string()),
+            mint_per_tx: msg.mint_per_tx.unwrap_or(1),
+            mint_fee: msg.mint_fee.unwrap_or_default(),
+            dev_fee: msg.dev_fee.unwrap_or_default(),
+            supply_limit: msg.supply_limit.unwrap_or(100000),
+            sale_time: msg.sale_time.unwrap_or(u64::MAX),
+            sale_end_time: msg.sale_end_time,
+            sale_paused: false,
+            max_reserved: msg.max_reserved,
+            soft_cap: msg.soft_cap,
+            purchase_mode: msg.purchase_mode.unwrap_or_default(),
+            max_per_block: msg.max_per_block.filter(|max| *max > 0),
+            buy_cooldown: msg.buy_cooldown.filter(|cooldown| *cooldown > 0),
+            operator_filter: msg.operator_filter.unwrap_or_default(),
+            compliance: msg
+                .compliance
+                .map(|address| deps.api.addr_validate(&address).map(String::from))
+                .transpose()?,
+            voucher_signer: msg.voucher_signer,
+            burns_free_supply: msg.burns_free_supply.unwrap_or(false),
+        };
+        assert_purchase_mode(&config.purchase_mode)?;
+        assert_token_id_scheme(&config.token_id_scheme)?;
+        if config.soft_cap.is_some() && config.sale_end_time.is_none() {
+            return Err(ContractError::SoftCapWithoutEndTime {});
+        }
+        if let Some(pubkey) = &config.voucher_signer {
+            assert_voucher_signer(pubkey)?;
+        }
+        self.config.save(deps.storage, &config)?;
+        let sale_state = SaleState {
+            reserved_amount: msg.reserved_amount.unwrap_or_default(),
+            ..SaleState::default()
+        };
+        self.sale_state.save(deps.storage, &sale_state)?;
+        if let Some(history_depth) = msg.history_depth.filter(|depth| *depth > 0) {
+            self.history_depth.save(deps.storage, &history_depth)?;
+        }
 
         let owner = match msg.minter {
             Some(owner) => deps.api.addr_validate(&owner)?,
@@ -64,10 +104,6 @@ where
         };
         cw_ownable::initialize_owner(deps.storage, deps.api, Some(owner.as_ref()))?;
 
-        if let Some(address) = msg.withdraw_address {
-            self.set_withdraw_address(deps, &owner, address)?;
-        }
-
         Ok(Response::default())
     }
 
@@ -103,6 +139,23 @@ where
                 self.approve_all(deps, env, info, operator, expires)
             }
             ExecuteMsg::RevokeAll { operator } => self.revoke_all(deps, env, info, operator),
+            ExecuteMsg::Permit {
+                owner_pubkey,
+                spender,
+                token_id,
+                expires,
+                nonce,
+                signature,
+            } => self.permit(
+                deps,
+                env,
+                owner_pubkey,
+                spender,
+                token_id,
+                expires,
+                nonce,
+                signature,
+            ),
             ExecuteMsg::TransferNft {
                 recipient,
                 token_id,
@@ -124,7 +177,9 @@ where
             ExecuteMsg::RemoveWithdrawAddress {} => {
                 self.remove_withdraw_address(deps.storage, &info.sender)
             }
-            ExecuteMsg::WithdrawFunds { amount } => self.withdraw_funds(deps.storage, &amount),
+            ExecuteMsg::WithdrawFunds { amount } => {
+                self.withdraw_funds(deps.as_ref(), &env, &amount)
+            }
             ExecuteMsg::SetName { name } => self.set_name(deps.storage, &info.sender, &name),
             ExecuteMsg::SetSymbol { symbol } => {
                 self.set_symbol(deps.storage, &info.sender, &symbol)
@@ -139,9 +194,102 @@ where
             ExecuteMsg::SetSaleTime { sale_time } => {
                 self.set_sale_time(deps, &info.sender, &sale_time)
             }
+            ExecuteMsg::UpdateConfig(update) => self.update_config(deps, &info.sender, update),
             ExecuteMsg::Buy { qty, extension } => self.buy(deps, env, info, &qty, extension),
-            ExecuteMsg::Reserve { qty, extension } => self.reserve(deps, info, &qty, extension),
+            ExecuteMsg::Reserve {
+                qty,
+                extension,
+                recipient,
+            } => self.reserve(deps, env, info, &qty, extension, recipient),
+            ExecuteMsg::SetSaleEndTime { sale_end_time } => {
+                self.set_sale_end_time(deps, &info.sender, sale_end_time)
+            }
+            ExecuteMsg::BuyWithVoucher {
+                qty,
+                max_qty,
+                price,
+                expires,
+                nonce,
+                signature,
+                extension,
+            } => self.buy_with_voucher(
+                deps, env, info, qty, max_qty, price, expires, nonce, signature, extension,
+            ),
+            ExecuteMsg::SetVoucherSigner { pubkey } => {
+                self.set_voucher_signer(deps, &info.sender, pubkey)
+            }
+            ExecuteMsg::SetCompliance { address } => {
+                self.set_compliance(deps, &info.sender, address)
+            }
+            ExecuteMsg::AddToBlocklist { addresses } => {
+                self.add_to_blocklist(deps, &info.sender, addresses)
+            }
+            ExecuteMsg::RemoveFromBlocklist { addresses } => {
+                self.remove_from_blocklist(deps, &info.sender, addresses)
+            }
+            ExecuteMsg::PruneExpired {
+                token_ids,
+                owners,
+                start_after,
+                limit,
+            } => self.prune_expired(deps, env, token_ids, owners, start_after, limit),
+            ExecuteMsg::UpdateOperatorFilter { add, remove } => {
+                self.update_operator_filter(deps, &info.sender, add, remove)
+            }
+            ExecuteMsg::CommitBuy { hash } => self.commit_buy(deps, env, info, hash),
+            ExecuteMsg::RevealBuy {
+                qty,
+                salt,
+                extension,
+            } => self.reveal_buy(deps, env, info, qty, salt, extension),
+            ExecuteMsg::RefundCommitment {} => self.refund_commitment(deps, env, info),
+            ExecuteMsg::ClaimRefund {} => self.claim_refund(deps, env, info),
+            ExecuteMsg::ReleaseFunds {} => self.release_funds(deps, &info.sender),
             ExecuteMsg::ToggleSaleActive {} => self.toggle_sale_active(deps, env, &info.sender),
+            ExecuteMsg::AddRecipe {
+                inputs,
+                output_uri,
+                output_extension,
+                supply_cap,
+                count_in_supply,
+            } => {
+                let recipe = Recipe {
+                    inputs,
+                    output_uri,
+                    output_extension,
+                    supply_cap,
+                    count_in_supply,
+                    redeemed: 0,
+                };
+                self.add_recipe(deps, &info.sender, recipe)
+            }
+            ExecuteMsg::RemoveRecipe { recipe_id } => {
+                self.remove_recipe(deps, &info.sender, recipe_id)
+            }
+            ExecuteMsg::Redeem {
+                recipe_id,
+                token_ids,
+            } => self.redeem(deps, env, info, recipe_id, token_ids),
+            ExecuteMsg::Receive(wrapper) => self.receive(deps, env, info, wrapper),
+            ExecuteMsg::SetCw20Price {
+                token,
+                mint_fee,
+                dev_fee,
+            } => self.set_cw20_price(deps, &info.sender, token, Price { mint_fee, dev_fee }),
+            ExecuteMsg::RemoveCw20Price { token } => {
+                self.remove_cw20_price(deps, &info.sender, token)
+            }
+            ExecuteMsg::SetDenomPrice {
+                denom,
+                mint_fee,
+                dev_fee,
+            } => self.set_denom_price(deps, &info.sender, denom, Price { mint_fee, dev_fee }),
+            ExecuteMsg::RemoveDenomPrice { denom } => {
+                self.remove_denom_price(deps, &info.sender, denom)
+            }
+            ExecuteMsg::SetHistoryDepth { depth } => {
+                self.set_history_depth(deps, &info.sender, depth)
+            }
         }
     }
 }
@@ -153,31 +301,43 @@ where
     E: CustomMsg,
     Q: CustomMsg,
 {
+    /// Mints `qty` NFTs to `owner` and returns their token ids, `sender` is recorded
+    /// as the minter in their history. With a `seed`, the NFTs are drawn at random
+    /// from the remaining supply instead of taking the next indexes.
+    #[allow(clippy::too_many_arguments)]
     pub fn mint(
         &self,
         deps: DepsMut,
-        info: MessageInfo,
-        token_id: String,
+        env: &Env,
+        sender: &Addr,
+        owner: &Addr,
+        config: &Config,
         qty: u64,
         extension: T,
-    ) -> Result<Response<C>, ContractError> {
-        let mut total_supply = self
-            .total_supply
-            .may_load(deps.storage)?
-            .unwrap_or_else(|| 0u64);
-        let base_uri = self
-            .base_uri
-            .may_load(deps.storage)?
-            .unwrap_or_else(|| "".into());
+        seed: Option<&[u8]>,
+    ) -> Result<Vec<String>, ContractError> {
+        let mut sale_state = self.sale_state.load(deps.storage)?;
+        let mint_cursor = sale_state.mint_cursor;
+        let remaining_supply = sale_state.remaining_supply(config);
+        let mut token_ids = Vec::with_capacity(qty as usize);
         // create the token
-        for i in 0..qty.clone() {
+        for i in 0..qty {
+            let next = mint_cursor + i;
+            let pick = match seed {
+                Some(seed) => next + random_u64(seed, i) % remaining_supply.saturating_sub(i).max(1),
+                None => next,
+            };
+            let index = self.take_pool_index(deps.storage, next, pick)?;
+            let new_token_id = config
+                .token_id_scheme
+                .token_id(&config.token_id_base, index);
             let token = TokenInfo {
-                owner: info.clone().sender,
+                owner: owner.clone(),
                 approvals: vec![],
-                token_uri: Some(format!("{:?}/{}", base_uri, total_supply.clone() + i + 1)),
+                token_uri: Some(config.token_uri(&new_token_id, index)),
                 extension: extension.clone(),
+                index: Some(index),
             };
-            let new_token_id = format!("{} #{}", token_id.clone(), total_supply.clone() + i + 1);
             self.tokens
                 .update(deps.storage, &new_token_id, |old| match old {
                     Some(_) => Err(ContractError::Claimed {}),
@@ -185,15 +345,18 @@ where
                 })?;
 
             self.increment_tokens(deps.storage)?;
+            self.increment_balance(deps.storage, owner)?;
+            let record =
+                HistoryRecord::new(HistoryAction::Mint, sender, None, Some(owner), &env.block);
+            self.push_history(deps.storage, &new_token_id, &record)?;
+            token_ids.push(new_token_id);
         }
-        total_supply += qty.clone();
-        self.total_supply.save(deps.storage, &total_supply)?;
+        sale_state.total_supply += qty;
+        sale_state.mint_cursor += qty;
+        sale_state.minted += qty;
+        self.sale_state.save(deps.storage, &sale_state)?;
 
-        Ok(Response::new()
-            .add_attribute("action", "mint")
-            .add_attribute("minter", info.clone().sender)
-            .add_attribute("owner", info.clone().sender)
-            .add_attribute("token_id", token_id))
+        Ok(token_ids)
     }
 
     pub fn update_ownership(
@@ -213,8 +376,13 @@ where
         address: String,
     ) -> Result<Response<C>, ContractError> {
         cw_ownable::assert_owner(deps.storage, sender)?;
-        deps.api.addr_validate(&address)?;
-        self.withdraw_address.save(deps.storage, &address)?;
+        self.apply_config_update(
+            deps,
+            ConfigUpdate {
+                withdraw_address: Some(address.clone()),
+                ..ConfigUpdate::default()
+            },
+        )?;
         Ok(Response::new()
             .add_attribute("action", "set_withdraw_address")
             .add_attribute("address", address))
@@ -227,8 +395,13 @@ where
         address: String,
     ) -> Result<Response<C>, ContractError> {
         cw_ownable::assert_owner(deps.storage, sender)?;
-        deps.api.addr_validate(&address)?;
-        self.dev_wallet.save(deps.storage, &address)?;
+        self.apply_config_update(
+            deps,
+            ConfigUpdate {
+                dev_wallet: Some(address.clone()),
+                ..ConfigUpdate::default()
+            },
+        )?;
         Ok(Response::new()
             .add_attribute("action", "set_dev_wallet")
             .add_attribute("address", address))
@@ -240,10 +413,10 @@ where
         sender: &Addr,
     ) -> Result<Response<C>, ContractError> {
         cw_ownable::assert_owner(storage, sender)?;
-        let address = self.withdraw_address.may_load(storage)?;
-        match address {
+        let mut config = self.config.load(storage)?;
+        match config.withdraw_address.take() {
             Some(address) => {
-                self.withdraw_address.remove(storage);
+                self.config.save(storage, &config)?;
                 Ok(Response::new()
                     .add_attribute("action", "remove_withdraw_address")
                     .add_attribute("address", address))
@@ -254,10 +427,20 @@ where
 
     pub fn withdraw_funds(
         &self,
-        storage: &mut dyn Storage,
+        deps: Deps,
+        env: &Env,
         amount: &Coin,
     ) -> Result<Response<C>, ContractError> {
-        let address = self.withdraw_address.may_load(storage)?;
+        // escrowed proceeds and pending commitments stay in the contract for refunds
+        let balance = deps
+            .querier
+            .query_balance(&env.contract.address, &amount.denom)?
+            .amount;
+        let available = balance.saturating_sub(self.locked_funds(deps.storage, &amount.denom)?);
+        if amount.amount > available {
+            return Err(ContractError::FundsLocked { available });
+        }
+        let address = self.config.load(deps.storage)?.withdraw_address;
         match address {
             Some(address) => {
                 let msg = BankMsg::Send {
@@ -334,9 +517,15 @@ where
     ) -> Result<Response<C>, ContractError> {
         cw_ownable::assert_owner(deps.storage, sender)?;
 
-        self.base_uri.save(deps.storage, &base_uri)?;
+        self.apply_config_update(
+            deps,
+            ConfigUpdate {
+                base_uri: Some(base_uri.clone()),
+                ..ConfigUpdate::default()
+            },
+        )?;
         Ok(Response::new()
-            .add_attribute("action", "set_mint_per_tx")
+            .add_attribute("action", "set_base_uri")
             .add_attribute("base_uri", base_uri.to_string()))
     }
 
@@ -348,7 +537,13 @@ where
     ) -> Result<Response<C>, ContractError> {
         cw_ownable::assert_owner(deps.storage, sender)?;
 
-        self.mint_per_tx.save(deps.storage, &tx)?;
+        self.apply_config_update(
+            deps,
+            ConfigUpdate {
+                mint_per_tx: Some(*tx),
+                ..ConfigUpdate::default()
+            },
+        )?;
         Ok(Response::new()
             .add_attribute("action", "set_mint_per_tx")
             .add_attribute("mint_per_tx", tx.to_string()))
@@ -362,7 +557,13 @@ where
     ) -> Result<Response<C>, ContractError> {
         cw_ownable::assert_owner(deps.storage, sender)?;
 
-        self.mint_fee.save(deps.storage, &fee)?;
+        self.apply_config_update(
+            deps,
+            ConfigUpdate {
+                mint_fee: Some(*fee),
+                ..ConfigUpdate::default()
+            },
+        )?;
         Ok(Response::new()
             .add_attribute("action", "set_mint_fee")
             .add_attribute("mint_fee", fee.to_string()))
@@ -376,7 +577,13 @@ where
     ) -> Result<Response<C>, ContractError> {
         cw_ownable::assert_owner(deps.storage, sender)?;
 
-        self.dev_fee.save(deps.storage, &fee)?;
+        self.apply_config_update(
+            deps,
+            ConfigUpdate {
+                dev_fee: Some(*fee),
+                ..ConfigUpdate::default()
+            },
+        )?;
         Ok(Response::new()
             .add_attribute("action", "set_dev_fee")
             .add_attribute("dev_fee", fee.to_string()))
@@ -390,7 +597,13 @@ where
     ) -> Result<Response<C>, ContractError> {
         cw_ownable::assert_owner(deps.storage, sender)?;
 
-        self.supply_limit.save(deps.storage, &supply_limit)?;
+        self.apply_config_update(
+            deps,
+            ConfigUpdate {
+                supply_limit: Some(*supply_limit),
+                ..ConfigUpdate::default()
+            },
+        )?;
         Ok(Response::new()
             .add_attribute("action", "set_supply_limit")
             .add_attribute("supply_limit", supply_limit.to_string()))
@@ -404,329 +617,1319 @@ where
     ) -> Result<Response<C>, ContractError> {
         cw_ownable::assert_owner(deps.storage, sender)?;
 
-        self.sale_time.save(deps.storage, &sale_time)?;
+        self.apply_config_update(
+            deps,
+            ConfigUpdate {
+                sale_time: Some(*sale_time),
+                ..ConfigUpdate::default()
+            },
+        )?;
         Ok(Response::new()
             .add_attribute("action", "set_sale_time")
             .add_attribute("sale_time", sale_time.to_string()))
     }
 
-    pub fn buy(
+    pub fn set_sale_end_time(
         &self,
         deps: DepsMut,
-        env: Env,
-        info: MessageInfo,
-        qty: &u64,
-        extension: T,
+        sender: &Addr,
+        sale_end_time: Option<u64>,
     ) -> Result<Response<C>, ContractError> {
-        let sale_time = self
-            .sale_time
-            .may_load(deps.storage)?
-            .unwrap_or_else(|| 0u64);
-        let sale_active = sale_time <= env.block.time.seconds();
-        if sale_active == false {
-            return Err(ContractError::SaleUnactivate {});
-        }
-        let sent_funds: u128 = info
-            .funds
-            .iter()
-            .find(|coin| coin.denom == "unibi")
-            .map_or(0u128, |coin| coin.amount.u128());
-        let mint_fee = self.mint_fee.may_load(deps.storage)?.unwrap_or_else(|| 0);
-        let dev_fee = self.dev_fee.may_load(deps.storage)?.unwrap_or_else(|| 0);
-        let mint_per_tx = self.mint_per_tx.may_load(deps.storage)?;
-        let total_fee = mint_fee.clone() + dev_fee.clone();
-        let token_id_base = self
-            .token_id_base
-            .may_load(deps.storage)?
-            .unwrap_or_default();
-        let withdraw_address = self
-            .withdraw_address
-            .may_load(deps.storage)?
-            .unwrap_or_else(|| info.clone().sender.into_string());
-        let dev_wallet = self
-            .dev_wallet
-            .may_load(deps.storage)?
-            .unwrap_or_else(|| info.clone().sender.into_string());
-
-        if sent_funds.clone() < qty.clone() as u128 * total_fee.clone() as u128 {
-            return Err(ContractError::IncorrectFunds {});
-        }
+        cw_ownable::assert_owner(deps.storage, sender)?;
 
-        let supply_limit = self
-            .supply_limit
-            .may_load(deps.storage)?
-            .unwrap_or_else(|| 1000u64);
-        let total_supply = self
-            .total_supply
-            .may_load(deps.storage)?
-            .unwrap_or_else(|| 0u64);
-        let mut real_purchase = cmp::min(qty.clone(), mint_per_tx.unwrap_or_else(|| 1u64));
-        real_purchase = cmp::min(real_purchase.clone(), supply_limit - total_supply);
-        let mut msg = Response::new();
-        let _mint_response: Response<C> = self.mint(
-            deps,
-            info.clone(),
-            token_id_base,
-            real_purchase.clone(),
-            extension.clone(),
-        )?;
-        let refund_amount =
-            sent_funds.clone() - total_fee.clone() as u128 * real_purchase.clone() as u128;
-        if refund_amount > 0 {
-            let send_msg = BankMsg::Send {
-                to_address: info.sender.into_string(),
-                amount: vec![coin(refund_amount, "unibi")],
-            };
-            msg = msg.add_message(send_msg);
-        }
-        if mint_fee.clone() > 0 {
-            let mint_fee_send = BankMsg::Send {
-                to_address: withdraw_address.clone().to_string(),
-                amount: vec![coin(
-                    mint_fee.clone() as u128 * real_purchase.clone() as u128,
-                    "unibi",
-                )],
-            };
-            msg = msg.add_message(mint_fee_send);
-        }
-        if dev_fee.clone() > 0 {
-            let dev_fee_send = BankMsg::Send {
-                to_address: dev_wallet.clone().to_string(),
-                amount: vec![coin(
-                    dev_fee.clone() as u128 * real_purchase.clone() as u128,
-                    "unibi",
-                )],
-            };
-            msg = msg.add_message(dev_fee_send);
-        }
-        msg = msg.add_attribute("action", "buy");
-        Ok(msg)
+        let sale_state = self.sale_state.load(deps.storage)?;
+        self.config.update(deps.storage, |mut config| -> Result<_, ContractError> {
+            assert_escrow_end_time(&sale_state, &config, sale_end_time)?;
+            config.sale_end_time = sale_end_time;
+            assert_sale_window(&config)?;
+            Ok(config)
+        })?;
+        Ok(Response::new()
+            .add_attribute("action", "set_sale_end_time")
+            .add_attribute(
+                "sale_end_time",
+                sale_end_time.map_or_else(|| "none".to_string(), |t| t.to_string()),
+            ))
     }
 
-    pub fn reserve(
+    pub fn update_config(
         &self,
         deps: DepsMut,
-        info: MessageInfo,
-        qty: &u64,
-        extension: T,
+        sender: &Addr,
+        update: ConfigUpdate,
     ) -> Result<Response<C>, ContractError> {
-        cw_ownable::assert_owner(deps.storage, &info.clone().sender)?;
-
-        let mint_per_tx = self
-            .mint_per_tx
-            .may_load(deps.storage)?
-            .unwrap_or_else(|| 1000u64);
-
-        let supply_limit = self
-            .supply_limit
-            .may_load(deps.storage)?
-            .unwrap_or_else(|| 1000u64);
-        let total_supply = self
-            .total_supply
-            .may_load(deps.storage)?
-            .unwrap_or_else(|| 0u64);
-        let mut reserved_amount = self
-            .reserved_amount
-            .may_load(deps.storage)?
-            .unwrap_or_else(|| 0u64);
-        let mut real_purchase = cmp::min(qty.clone(), supply_limit - total_supply);
-        real_purchase = cmp::min(real_purchase.clone(), mint_per_tx);
-        let token_id_base = self
-            .token_id_base
-            .may_load(deps.storage)?
-            .unwrap_or_default();
-
-        reserved_amount += real_purchase.clone();
-        self.reserved_amount.save(deps.storage, &reserved_amount)?;
-
-        let mut msg = Response::new();
-        let _mint_response: Response<C> = self.mint(
-            deps,
-            info.clone(),
-            token_id_base,
-            qty.clone(),
-            extension.clone(),
-        )?;
+        cw_ownable::assert_owner(deps.storage, sender)?;
 
-        msg = msg
-            .add_attribute("action", "reserve")
-            .add_attribute("new_reserved", real_purchase.clone().to_string())
-            .add_attribute("total_reserved_amount", reserved_amount.clone().to_string());
-        Ok(msg)
+        let config = self.apply_config_update(deps, update)?;
+        Ok(Response::new()
+            .add_attribute("action", "update_config")
+            .add_attribute("mint_per_tx", config.mint_per_tx.to_string())
+            .add_attribute("mint_fee", config.mint_fee.to_string())
+            .add_attribute("dev_fee", config.dev_fee.to_string())
+            .add_attribute("supply_limit", config.supply_limit.to_string())
+            .add_attribute("sale_time", config.sale_time.to_string()))
     }
 
-    pub fn toggle_sale_active(
+    pub fn set_history_depth(
         &self,
         deps: DepsMut,
-        env: Env,
         sender: &Addr,
+        depth: Option<u64>,
     ) -> Result<Response<C>, ContractError> {
         cw_ownable::assert_owner(deps.storage, sender)?;
 
-        let sale_time = self
-            .sale_time
-            .may_load(deps.storage)?
-            .unwrap_or_else(|| 0u64);
-        if sale_time.clone() <= env.block.time.seconds() {
-            self.sale_time.save(deps.storage, &std::u64::MAX)?;
-            Ok(Response::new()
-                .add_attribute("action", "toggle_sale_active")
-                .add_attribute("sale_active", false.to_string()))
-        } else {
-            self.sale_time.save(deps.storage, &0u64)?;
-            Ok(Response::new()
-                .add_attribute("action", "toggle_sale_active")
-                .add_attribute("sale_active", true.to_string()))
+        // a depth of 0 would drop every record as it is written, it removes the cap instead
+        let depth = depth.filter(|depth| *depth > 0);
+        match depth {
+            Some(depth) => self.history_depth.save(deps.storage, &depth)?,
+            None => self.history_depth.remove(deps.storage),
         }
+        Ok(Response::new()
+            .add_attribute("action", "set_history_depth")
+            .add_attribute(
+                "history_depth",
+                depth.map_or_else(|| "unlimited".to_string(), |d| d.to_string()),
+            ))
     }
-}
 
-impl<'a, T, C, E, Q> Cw721Execute<T, C> for Cw721Contract<'a, T, C, E, Q>
-where
-    T: Serialize + DeserializeOwned + Clone,
-    C: CustomMsg,
-    E: CustomMsg,
-    Q: CustomMsg,
-{
-    type Err = ContractError;
-
-    fn transfer_nft(
+    pub fn buy(
         &self,
         deps: DepsMut,
         env: Env,
         info: MessageInfo,
-        recipient: String,
-        token_id: String,
+        qty: &u64,
+        extension: T,
     ) -> Result<Response<C>, ContractError> {
-        self._transfer_nft(deps, &env, &info, &recipient, &token_id)?;
-
-        Ok(Response::new()
-            .add_attribute("action", "transfer_nft")
-            .add_attribute("sender", info.sender)
-            .add_attribute("recipient", recipient)
-            .add_attribute("token_id", token_id))
+        let config = self.config.load(deps.storage)?;
+        if config.purchase_mode != PurchaseMode::Direct {
+            return Err(ContractError::DirectBuyDisabled {});
+        }
+        self.assert_sale_active(deps.storage, &config, &env)?;
+        self.assert_not_blocklisted(deps.storage, &info.sender)?;
+        let payment = match info.funds.as_slice() {
+            [] => return Err(ContractError::IncorrectFunds {}),
+            [payment] => payment.clone(),
+            _ => return Err(ContractError::MultipleDenoms {}),
+        };
+        let price = self
+            .denom_price(deps.storage, &config, &payment.denom)?
+            .ok_or_else(|| ContractError::UnacceptedDenom {
+                denom: payment.denom.clone(),
+            })?;
+        self.buy_at_price(
+            deps, &env, &info, &config, payment, price, *qty, extension, "buy",
+        )
     }
 
-    fn send_nft(
+    #[allow(clippy::too_many_arguments)]
+    pub fn buy_with_voucher(
         &self,
-        deps: DepsMut,
+        mut deps: DepsMut,
         env: Env,
         info: MessageInfo,
-        contract: String,
-        token_id: String,
-        msg: Binary,
+        qty: u64,
+        max_qty: u64,
+        price: Coin,
+        expires: u64,
+        nonce: u64,
+        signature: Binary,
+        extension: T,
     ) -> Result<Response<C>, ContractError> {
-        // Transfer token
-        self._transfer_nft(deps, &env, &info, &contract, &token_id)?;
+        let config = self.config.load(deps.storage)?;
+        if config.purchase_mode != PurchaseMode::Direct {
+            return Err(ContractError::DirectBuyDisabled {});
+        }
+        self.assert_sale_active(deps.storage, &config, &env)?;
+        self.assert_not_blocklisted(deps.storage, &info.sender)?;
+        let signer = config
+            .voucher_signer
+            .clone()
+            .ok_or(ContractError::NoVoucherSigner {})?;
+
+        let payload = VoucherPayload {
+            chain_id: env.block.chain_id.clone(),
+            contract: env.contract.address.to_string(),
+            buyer: info.sender.to_string(),
+            max_qty,
+            price: price.clone(),
+            expires,
+            nonce,
+        };
+        let valid = deps
+            .api
+            .secp256k1_verify(&payload.hash()?, &signature, &signer)
+            .unwrap_or(false);
+        if !valid {
+            return Err(ContractError::InvalidVoucher {});
+        }
+        if env.block.time.seconds() >= expires {
+            return Err(ContractError::VoucherExpired {});
+        }
+        if self.used_vouchers.has(deps.storage, nonce) {
+            return Err(ContractError::VoucherUsed { nonce });
+        }
+        if qty > max_qty {
+            return Err(ContractError::VoucherQtyExceeded { max_qty });
+        }
 
-        let send = Cw721ReceiveMsg {
-            sender: info.sender.to_string(),
-            token_id: token_id.clone(),
-            msg,
+        let payment = match info.funds.as_slice() {
+            [] => return Err(ContractError::IncorrectFunds {}),
+            [payment] if payment.denom == price.denom => payment.clone(),
+            [payment] => {
+                return Err(ContractError::UnacceptedDenom {
+                    denom: payment.denom.clone(),
+                })
+            }
+            _ => return Err(ContractError::MultipleDenoms {}),
+        };
+        // the dev fee of the denom is paid out of the voucher price
+        let dev_fee = self
+            .denom_price(deps.storage, &config, &price.denom)?
+            .map_or(Uint128::zero(), |price| price.dev_fee)
+            .min(price.amount);
+        let price = Price {
+            mint_fee: price.amount - dev_fee,
+            dev_fee,
         };
+        let res = self.buy_at_price(
+            deps.branch(),
+            &env,
+            &info,
+            &config,
+            payment,
+            price,
+            qty,
+            extension,
+            "buy_with_voucher",
+        )?;
+        self.used_vouchers.save(deps.storage, nonce, &Empty {})?;
 
-        // Send message
-        Ok(Response::new()
-            .add_message(send.into_cosmos_msg(contract.clone())?)
-            .add_attribute("action", "send_nft")
-            .add_attribute("sender", info.sender)
-            .add_attribute("recipient", contract)
-            .add_attribute("token_id", token_id))
+        Ok(res.add_attribute("nonce", nonce.to_string()))
     }
 
-    fn approve(
+    /// Mints `qty` NFTs to the buyer paying `price` each in native `payment`, and
+    /// refunds what isn't used
+    #[allow(clippy::too_many_arguments)]
+    fn buy_at_price(
         &self,
-        deps: DepsMut,
-        env: Env,
-        info: MessageInfo,
-        spender: String,
-        token_id: String,
-        expires: Option<Expiration>,
+        mut deps: DepsMut,
+        env: &Env,
+        info: &MessageInfo,
+        config: &Config,
+        payment: Coin,
+        price: Price,
+        qty: u64,
+        extension: T,
+        action: &str,
     ) -> Result<Response<C>, ContractError> {
-        self._update_approvals(deps, &env, &info, &spender, &token_id, true, expires)?;
+        if payment.amount < price.total() * Uint128::from(qty) {
+            return Err(ContractError::IncorrectFunds {});
+        }
 
-        Ok(Response::new()
-            .add_attribute("action", "approve")
-            .add_attribute("sender", info.sender)
-            .add_attribute("spender", spender)
-            .add_attribute("token_id", token_id))
+        let real_purchase = self.purchasable(deps.storage, config, qty)?;
+        let real_purchase =
+            self.apply_rate_limits(deps.storage, config, env, &info.sender, real_purchase)?;
+        let denom = Denom::Native(payment.denom.clone());
+        let mint_fee = price.mint_fee * Uint128::from(real_purchase);
+        let dev_fee = price.dev_fee * Uint128::from(real_purchase);
+        self.record_purchase(deps.storage, &info.sender, real_purchase, &denom, mint_fee, dev_fee)?;
+        let mut msg = Response::new();
+        let token_ids = self.mint(
+            deps.branch(),
+            env,
+            &info.sender,
+            &info.sender,
+            config,
+            real_purchase,
+            extension,
+            None,
+        )?;
+        let refund_amount = payment.amount - price.total() * Uint128::from(real_purchase);
+        if !refund_amount.is_zero() {
+            let send_msg = BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![coin(refund_amount.u128(), &payment.denom)],
+            };
+            msg = msg.add_message(send_msg);
+        }
+        let fee_msgs = self.pay_fees(
+            deps.storage,
+            config,
+            &info.sender,
+            token_ids,
+            &denom,
+            mint_fee,
+            dev_fee,
+        )?;
+        msg = msg
+            .add_messages(fee_msgs)
+            .add_attribute("action", action)
+            .add_attribute("denom", payment.denom);
+        Ok(msg)
     }
 
-    fn revoke(
+    pub fn commit_buy(
         &self,
         deps: DepsMut,
         env: Env,
         info: MessageInfo,
-        spender: String,
-        token_id: String,
+        hash: Binary,
     ) -> Result<Response<C>, ContractError> {
-        self._update_approvals(deps, &env, &info, &spender, &token_id, false, None)?;
+        let config = self.config.load(deps.storage)?;
+        let (reveal_delay, reveal_timeout) = match config.purchase_mode {
+            PurchaseMode::CommitReveal {
+                reveal_delay,
+                reveal_timeout,
+            } => (reveal_delay, reveal_timeout),
+            PurchaseMode::Direct => return Err(ContractError::CommitRevealDisabled {}),
+        };
+        self.assert_sale_active(deps.storage, &config, &env)?;
+        self.assert_not_blocklisted(deps.storage, &info.sender)?;
+        let payment = match info.funds.as_slice() {
+            [payment] => payment.clone(),
+            [] => return Err(ContractError::IncorrectFunds {}),
+            _ => return Err(ContractError::MultipleDenoms {}),
+        };
+        if self.denom_price(deps.storage, &config, &payment.denom)?.is_none() {
+            return Err(ContractError::UnacceptedDenom {
+                denom: payment.denom,
+            });
+        }
+        if self.commitments.has(deps.storage, &info.sender) {
+            return Err(ContractError::CommitmentPending {});
+        }
 
+        // the salt stays hidden and the block is not known when signing the commitment
+        let entropy = Sha256::new()
+            .chain_update(hash.as_slice())
+            .chain_update(info.sender.as_bytes())
+            .chain_update(env.block.height.to_be_bytes())
+            .chain_update(env.block.time.nanos().to_be_bytes())
+            .chain_update(env.transaction.map_or(0, |tx| tx.index).to_be_bytes())
+            .finalize();
+        let commitment = Commitment {
+            hash,
+            payment,
+            reveal_from: env.block.height + reveal_delay,
+            expires: env.block.height + reveal_timeout,
+            entropy: Binary::from(entropy.as_slice()),
+        };
+        self.save_commitment(deps.storage, &info.sender, &commitment)?;
         Ok(Response::new()
-            .add_attribute("action", "revoke")
-            .add_attribute("sender", info.sender)
-            .add_attribute("spender", spender)
-            .add_attribute("token_id", token_id))
+            .add_attribute("action", "commit_buy")
+            .add_attribute("buyer", info.sender)
+            .add_attribute("reveal_from", commitment.reveal_from.to_string())
+            .add_attribute("expires", commitment.expires.to_string()))
     }
 
-    fn approve_all(
+    pub fn reveal_buy(
         &self,
-        deps: DepsMut,
+        mut deps: DepsMut,
         env: Env,
         info: MessageInfo,
-        operator: String,
-        expires: Option<Expiration>,
+        qty: u64,
+        salt: Binary,
+        extension: T,
     ) -> Result<Response<C>, ContractError> {
-        // reject expired data as invalid
-        let expires = expires.unwrap_or_default();
-        if expires.is_expired(&env.block) {
-            return Err(ContractError::Expired {});
+        self.assert_not_blocklisted(deps.storage, &info.sender)?;
+        let commitment = self
+            .commitments
+            .may_load(deps.storage, &info.sender)?
+            .ok_or(ContractError::NoCommitment {})?;
+        if env.block.height < commitment.reveal_from {
+            return Err(ContractError::RevealTooEarly {
+                reveal_from: commitment.reveal_from,
+            });
+        }
+        if env.block.height >= commitment.expires {
+            return Err(ContractError::CommitmentExpired {});
+        }
+        if Commitment::hash(&info.sender, qty, &salt) != commitment.hash {
+            return Err(ContractError::InvalidReveal {});
         }
 
-        // set the operator for us
-        let operator_addr = deps.api.addr_validate(&operator)?;
-        self.operators
-            .save(deps.storage, (&info.sender, &operator_addr), &expires)?;
+        // a commitment can't be revealed into a paused, ended or failed sale, it is refunded
+        // once expired instead
+        let config = self.config.load(deps.storage)?;
+        self.assert_sale_active(deps.storage, &config, &env)?;
+        let payment = commitment.payment.clone();
+        let price = self
+            .denom_price(deps.storage, &config, &payment.denom)?
+            .ok_or_else(|| ContractError::UnacceptedDenom {
+                denom: payment.denom.clone(),
+            })?;
+        if payment.amount < price.total() * Uint128::from(qty) {
+            return Err(ContractError::IncorrectFunds {});
+        }
+        self.remove_commitment(deps.storage, &info.sender, &commitment)?;
+
+        let real_purchase = self.purchasable(deps.storage, &config, qty)?;
+        let real_purchase =
+            self.apply_rate_limits(deps.storage, &config, &env, &info.sender, real_purchase)?;
+        let denom = Denom::Native(payment.denom.clone());
+        let mint_fee = price.mint_fee * Uint128::from(real_purchase);
+        let dev_fee = price.dev_fee * Uint128::from(real_purchase);
+        self.record_purchase(deps.storage, &info.sender, real_purchase, &denom, mint_fee, dev_fee)?;
+        // the time of the reveal block is not known when the reveal is sent, so the
+        // buyer can't compute the draw beforehand and pick the block to reveal in
+        let seed = Sha256::new()
+            .chain_update(commitment.entropy.as_slice())
+            .chain_update(salt.as_slice())
+            .chain_update(env.block.height.to_be_bytes())
+            .chain_update(env.block.time.nanos().to_be_bytes())
+            .finalize();
+        let token_ids = self.mint(
+            deps.branch(),
+            &env,
+            &info.sender,
+            &info.sender,
+            &config,
+            real_purchase,
+            extension,
+            Some(seed.as_slice()),
+        )?;
 
-        Ok(Response::new()
-            .add_attribute("action", "approve_all")
-            .add_attribute("sender", info.sender)
-            .add_attribute("operator", operator))
+        let mut msg = Response::new();
+        let refund_amount = payment.amount - price.total() * Uint128::from(real_purchase);
+        if !refund_amount.is_zero() {
+            msg = msg.add_message(payment_msg::<C>(&denom, info.sender.to_string(), refund_amount)?);
+        }
+        let fee_msgs = self.pay_fees(
+            deps.storage,
+            &config,
+            &info.sender,
+            token_ids.clone(),
+            &denom,
+            mint_fee,
+            dev_fee,
+        )?;
+        Ok(msg
+            .add_messages(fee_msgs)
+            .add_attribute("action", "reveal_buy")
+            .add_attribute("buyer", info.sender)
+            .add_attribute("token_ids", token_ids.join(",")))
     }
 
-    fn revoke_all(
+    pub fn refund_commitment(
         &self,
         deps: DepsMut,
-        _env: Env,
+        env: Env,
         info: MessageInfo,
-        operator: String,
     ) -> Result<Response<C>, ContractError> {
-        let operator_addr = deps.api.addr_validate(&operator)?;
-        self.operators
-            .remove(deps.storage, (&info.sender, &operator_addr));
+        let commitment = self
+            .commitments
+            .may_load(deps.storage, &info.sender)?
+            .ok_or(ContractError::NoCommitment {})?;
+        if env.block.height < commitment.expires {
+            return Err(ContractError::CommitmentNotExpired {
+                expires: commitment.expires,
+            });
+        }
+        self.remove_commitment(deps.storage, &info.sender, &commitment)?;
 
         Ok(Response::new()
-            .add_attribute("action", "revoke_all")
-            .add_attribute("sender", info.sender)
-            .add_attribute("operator", operator))
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![commitment.payment],
+            })
+            .add_attribute("action", "refund_commitment")
+            .add_attribute("buyer", info.sender))
     }
 
-    fn burn(
+    pub fn receive(
         &self,
         deps: DepsMut,
         env: Env,
         info: MessageInfo,
-        token_id: String,
+        wrapper: Cw20ReceiveMsg,
     ) -> Result<Response<C>, ContractError> {
-        let token = self.tokens.load(deps.storage, &token_id)?;
-        self.check_can_send(deps.as_ref(), &env, &info, &token)?;
-
-        self.tokens.remove(deps.storage, &token_id)?;
-        self.decrement_tokens(deps.storage)?;
+        let buyer = deps.api.addr_validate(&wrapper.sender)?;
+        match from_json(&wrapper.msg)? {
+            ReceiveMsg::Buy { qty, extension } => {
+                self.buy_cw20(deps, env, info.sender, buyer, wrapper.amount, qty, extension)
+            }
+        }
+    }
 
-        Ok(Response::new()
-            .add_attribute("action", "burn")
-            .add_attribute("sender", info.sender)
+    #[allow(clippy::too_many_arguments)]
+    pub fn buy_cw20(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        token: Addr,
+        buyer: Addr,
+        amount: Uint128,
+        qty: u64,
+        extension: T,
+    ) -> Result<Response<C>, ContractError> {
+        let config = self.config.load(deps.storage)?;
+        if config.purchase_mode != PurchaseMode::Direct {
+            return Err(ContractError::DirectBuyDisabled {});
+        }
+        self.assert_sale_active(deps.storage, &config, &env)?;
+        self.assert_not_blocklisted(deps.storage, &buyer)?;
+        let price = self
+            .cw20_prices
+            .may_load(deps.storage, &token)?
+            .ok_or_else(|| ContractError::UnacceptedToken {
+                token: token.to_string(),
+            })?;
+
+        if amount < price.total() * Uint128::from(qty) {
+            return Err(ContractError::IncorrectFunds {});
+        }
+
+        let real_purchase = self.purchasable(deps.storage, &config, qty)?;
+        let real_purchase = self.apply_rate_limits(deps.storage, &config, &env, &buyer, real_purchase)?;
+        let denom = Denom::Cw20(token.clone());
+        let mint_fee = price.mint_fee * Uint128::from(real_purchase);
+        let dev_fee = price.dev_fee * Uint128::from(real_purchase);
+        self.record_purchase(deps.storage, &buyer, real_purchase, &denom, mint_fee, dev_fee)?;
+        let mut msg = Response::new();
+        let token_ids =
+            self.mint(deps.branch(), &env, &buyer, &buyer, &config, real_purchase, extension, None)?;
+        let refund_amount = amount - price.total() * Uint128::from(real_purchase);
+        if !refund_amount.is_zero() {
+            msg = msg.add_message(cw20_transfer(&token, buyer.to_string(), refund_amount)?);
+        }
+        let fee_msgs =
+            self.pay_fees(deps.storage, &config, &buyer, token_ids, &denom, mint_fee, dev_fee)?;
+        msg = msg
+            .add_messages(fee_msgs)
+            .add_attribute("action", "buy")
+            .add_attribute("token", token);
+        Ok(msg)
+    }
+
+    pub fn set_cw20_price(
+        &self,
+        deps: DepsMut,
+        sender: &Addr,
+        token: String,
+        price: Price,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, sender)?;
+
+        let token_addr = deps.api.addr_validate(&token)?;
+        self.cw20_prices.save(deps.storage, &token_addr, &price)?;
+        Ok(Response::new()
+            .add_attribute("action", "set_cw20_price")
+            .add_attribute("token", token)
+            .add_attribute("mint_fee", price.mint_fee)
+            .add_attribute("dev_fee", price.dev_fee))
+    }
+
+    pub fn remove_cw20_price(
+        &self,
+        deps: DepsMut,
+        sender: &Addr,
+        token: String,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, sender)?;
+
+        let token_addr = deps.api.addr_validate(&token)?;
+        if !self.cw20_prices.has(deps.storage, &token_addr) {
+            return Err(ContractError::UnacceptedToken { token });
+        }
+        self.cw20_prices.remove(deps.storage, &token_addr);
+        Ok(Response::new()
+            .add_attribute("action", "remove_cw20_price")
+            .add_attribute("token", token))
+    }
+
+    pub fn set_denom_price(
+        &self,
+        deps: DepsMut,
+        sender: &Addr,
+        denom: String,
+        price: Price,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, sender)?;
+
+        if denom == NATIVE_DENOM {
+            return Err(ContractError::DefaultDenom { denom });
+        }
+        self.denom_prices.save(deps.storage, &denom, &price)?;
+        Ok(Response::new()
+            .add_attribute("action", "set_denom_price")
+            .add_attribute("denom", denom)
+            .add_attribute("mint_fee", price.mint_fee)
+            .add_attribute("dev_fee", price.dev_fee))
+    }
+
+    pub fn remove_denom_price(
+        &self,
+        deps: DepsMut,
+        sender: &Addr,
+        denom: String,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, sender)?;
+
+        if denom == NATIVE_DENOM {
+            return Err(ContractError::DefaultDenom { denom });
+        }
+        if !self.denom_prices.has(deps.storage, &denom) {
+            return Err(ContractError::UnacceptedDenom { denom });
+        }
+        self.denom_prices.remove(deps.storage, &denom);
+        Ok(Response::new()
+            .add_attribute("action", "remove_denom_price")
+            .add_attribute("denom", denom))
+    }
+
+    pub fn reserve(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        qty: &u64,
+        extension: T,
+        recipient: Option<String>,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+        let recipient = match recipient {
+            Some(recipient) => deps.api.addr_validate(&recipient)?,
+            None => info.sender.clone(),
+        };
+        self.assert_not_blocklisted(deps.storage, &recipient)?;
+
+        let config = self.config.load(deps.storage)?;
+        let mut sale_state = self.sale_state.load(deps.storage)?;
+        let remaining_reserved = sale_state.remaining_reserved(&config);
+        let mut real_purchase = cmp::min(*qty, sale_state.remaining_supply(&config));
+        real_purchase = cmp::min(real_purchase, config.mint_per_tx);
+        if let Some(remaining_reserved) = remaining_reserved {
+            real_purchase = cmp::min(real_purchase, remaining_reserved);
+        }
+        if real_purchase == 0 && *qty > 0 {
+            return Err(match remaining_reserved {
+                Some(0) => ContractError::ReserveExhausted {},
+                _ => ContractError::SoldOut {},
+            });
+        }
+
+        sale_state.reserved_amount += real_purchase;
+        let reserved_amount = sale_state.reserved_amount;
+        self.sale_state.save(deps.storage, &sale_state)?;
+        self.record_reserve(deps.storage, real_purchase)?;
+        let reserved_for = self
+            .reserved_by
+            .may_load(deps.storage, &recipient)?
+            .unwrap_or_default()
+            + real_purchase;
+        self.reserved_by
+            .save(deps.storage, &recipient, &reserved_for)?;
+
+        let mut msg = Response::new();
+        self.mint(
+            deps,
+            &env,
+            &info.sender,
+            &recipient,
+            &config,
+            real_purchase,
+            extension,
+            None,
+        )?;
+
+        msg = msg
+            .add_attribute("action", "reserve")
+            .add_attribute("recipient", recipient)
+            .add_attribute("new_reserved", real_purchase.to_string())
+            .add_attribute("total_reserved_amount", reserved_amount.to_string());
+        Ok(msg)
+    }
+
+    /// Burns the NFTs bought by the sender and refunds their escrowed payments,
+    /// once the sale has ended below its soft cap
+    pub fn claim_refund(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+    ) -> Result<Response<C>, ContractError> {
+        let config = self.config.load(deps.storage)?;
+        let sale_state = self.sale_state.load(deps.storage)?;
+        if !sale_state.escrow_active(&config) {
+            return Err(ContractError::NoEscrow {});
+        }
+        let sold = self.sale_stats.may_load(deps.storage)?.unwrap_or_default().sold;
+        let soft_cap_missed = config.soft_cap.map_or(false, |soft_cap| sold < soft_cap);
+        let sale_ended = config
+            .sale_end_time
+            .map_or(false, |sale_end_time| sale_end_time <= env.block.time.seconds());
+        if !soft_cap_missed || !sale_ended {
+            return Err(ContractError::RefundUnavailable {});
+        }
+        let mut escrow = self
+            .escrows
+            .may_load(deps.storage, &info.sender)?
+            .ok_or(ContractError::NothingToRefund {})?;
+
+        // NFTs the buyer no longer holds stay in escrow, they are refunded if they come back
+        let mut held = vec![];
+        let mut moved = vec![];
+        for token_id in &escrow.token_ids {
+            let owner = self.tokens.may_load(deps.storage, token_id)?.map(|token| token.owner);
+            if owner.as_ref() == Some(&info.sender) {
+                held.push(token_id.clone());
+            } else {
+                moved.push(token_id.clone());
+            }
+        }
+        if held.is_empty() {
+            return Err(match moved.first() {
+                Some(token_id) => ContractError::EscrowedTokenMoved {
+                    token_id: token_id.clone(),
+                },
+                None => ContractError::NothingToRefund {},
+            });
+        }
+        for token_id in &held {
+            self.burn(deps.branch(), env.clone(), info.clone(), token_id.clone())?;
+        }
+
+        // the payments are refunded pro rata of the NFTs burned
+        let (burned, bought) = (held.len() as u128, escrow.token_ids.len() as u128);
+        let mut escrowed = self.escrowed.may_load(deps.storage)?.unwrap_or_default();
+        let mut refunded = Escrow {
+            token_ids: held,
+            payments: vec![],
+        };
+        let mut msg = Response::new();
+        for payment in &mut escrow.payments {
+            let share = EscrowedPayment {
+                denom: payment.denom.clone(),
+                mint_fee: payment.mint_fee.multiply_ratio(burned, bought),
+                dev_fee: payment.dev_fee.multiply_ratio(burned, bought),
+            };
+            payment.mint_fee -= share.mint_fee;
+            payment.dev_fee -= share.dev_fee;
+            if let Some(total) = escrowed.iter_mut().find(|total| total.denom == share.denom) {
+                total.mint_fee -= share.mint_fee;
+                total.dev_fee -= share.dev_fee;
+            }
+            if !share.total().is_zero() {
+                msg = msg.add_message(payment_msg::<C>(
+                    &share.denom,
+                    info.sender.to_string(),
+                    share.total(),
+                )?);
+            }
+            refunded.payments.push(share);
+        }
+        escrowed.retain(|total| !total.total().is_zero());
+        self.escrowed.save(deps.storage, &escrowed)?;
+        escrow.token_ids = moved;
+        escrow.payments.retain(|payment| !payment.total().is_zero());
+        if escrow.token_ids.is_empty() {
+            self.escrows.remove(deps.storage, &info.sender);
+        } else {
+            self.escrows.save(deps.storage, &info.sender, &escrow)?;
+        }
+        self.record_refund(deps.storage, &info.sender, &refunded)?;
+
+        Ok(msg
+            .add_attribute("action", "claim_refund")
+            .add_attribute("buyer", info.sender)
+            .add_attribute("burned", refunded.token_ids.len().to_string())
+            .add_attribute("kept_in_escrow", escrow.token_ids.len().to_string()))
+    }
+
+    /// Pays the escrowed proceeds out to the withdraw address and dev wallet once the
+    /// soft cap is reached, later sales are paid out right away
+    pub fn release_funds(
+        &self,
+        deps: DepsMut,
+        sender: &Addr,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, sender)?;
+
+        let config = self.config.load(deps.storage)?;
+        let mut sale_state = self.sale_state.load(deps.storage)?;
+        if !sale_state.escrow_active(&config) {
+            return Err(ContractError::NoEscrow {});
+        }
+        let soft_cap = config.soft_cap.unwrap_or_default();
+        let sold = self.sale_stats.may_load(deps.storage)?.unwrap_or_default().sold;
+        if sold < soft_cap {
+            return Err(ContractError::SoftCapNotReached { sold, soft_cap });
+        }
+
+        let mut msg = Response::new();
+        for payment in self.escrowed.may_load(deps.storage)?.unwrap_or_default() {
+            if !payment.mint_fee.is_zero() {
+                let withdraw_address = config
+                    .withdraw_address
+                    .clone()
+                    .ok_or(ContractError::NoWithdrawAddress {})?;
+                msg = msg.add_message(payment_msg::<C>(
+                    &payment.denom,
+                    withdraw_address,
+                    payment.mint_fee,
+                )?);
+            }
+            if !payment.dev_fee.is_zero() {
+                msg = msg.add_message(payment_msg::<C>(
+                    &payment.denom,
+                    config.dev_wallet.clone(),
+                    payment.dev_fee,
+                )?);
+            }
+        }
+        self.escrowed.remove(deps.storage);
+        sale_state.funds_released = true;
+        self.sale_state.save(deps.storage, &sale_state)?;
+
+        Ok(msg
+            .add_attribute("action", "release_funds")
+            .add_attribute("sold", sold.to_string()))
+    }
+
+    pub fn set_voucher_signer(
+        &self,
+        deps: DepsMut,
+        sender: &Addr,
+        pubkey: Option<Binary>,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, sender)?;
+
+        if let Some(pubkey) = &pubkey {
+            assert_voucher_signer(pubkey)?;
+        }
+        self.config.update(deps.storage, |mut config| -> StdResult<_> {
+            config.voucher_signer = pubkey.clone();
+            Ok(config)
+        })?;
+        Ok(Response::new()
+            .add_attribute("action", "set_voucher_signer")
+            .add_attribute(
+                "voucher_signer",
+                pubkey.map_or_else(|| "none".to_string(), |pubkey| pubkey.to_base64()),
+            ))
+    }
+
+    pub fn set_compliance(
+        &self,
+        deps: DepsMut,
+        sender: &Addr,
+        address: Option<String>,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, sender)?;
+
+        if let Some(address) = &address {
+            deps.api.addr_validate(address)?;
+        }
+        self.config.update(deps.storage, |mut config| -> StdResult<_> {
+            config.compliance = address.clone();
+            Ok(config)
+        })?;
+        Ok(Response::new()
+            .add_attribute("action", "set_compliance")
+            .add_attribute("compliance", address.unwrap_or_else(|| "none".to_string())))
+    }
+
+    pub fn add_to_blocklist(
+        &self,
+        deps: DepsMut,
+        sender: &Addr,
+        addresses: Vec<String>,
+    ) -> Result<Response<C>, ContractError> {
+        self.assert_compliance(deps.storage, sender)?;
+
+        for address in &addresses {
+            let address = deps.api.addr_validate(address)?;
+            self.blocklist.save(deps.storage, &address, &Empty {})?;
+        }
+        Ok(Response::new()
+            .add_attribute("action", "add_to_blocklist")
+            .add_attribute("count", addresses.len().to_string()))
+    }
+
+    pub fn remove_from_blocklist(
+        &self,
+        deps: DepsMut,
+        sender: &Addr,
+        addresses: Vec<String>,
+    ) -> Result<Response<C>, ContractError> {
+        self.assert_compliance(deps.storage, sender)?;
+
+        for address in &addresses {
+            let address = deps.api.addr_validate(address)?;
+            self.blocklist.remove(deps.storage, &address);
+        }
+        Ok(Response::new()
+            .add_attribute("action", "remove_from_blocklist")
+            .add_attribute("count", addresses.len().to_string()))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn permit(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        owner_pubkey: Binary,
+        spender: String,
+        token_id: Option<String>,
+        expires: Option<Expiration>,
+        nonce: u64,
+        signature: Binary,
+    ) -> Result<Response<C>, ContractError> {
+        // owners share the bech32 prefix of the contract
+        let (prefix, _, _) = bech32::decode(env.contract.address.as_str())
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+        let owner = deps
+            .api
+            .addr_validate(&pubkey_address(&prefix, &owner_pubkey)?)?;
+
+        let expected = self
+            .permit_nonces
+            .may_load(deps.storage, &owner)?
+            .unwrap_or_default();
+        if nonce != expected {
+            return Err(ContractError::InvalidNonce { expected });
+        }
+        let payload = PermitPayload {
+            chain_id: env.block.chain_id.clone(),
+            contract: env.contract.address.to_string(),
+            spender: spender.clone(),
+            token_id: token_id.clone(),
+            expires,
+            nonce,
+        };
+        let valid = deps
+            .api
+            .secp256k1_verify(&payload.hash()?, &signature, &owner_pubkey)
+            .unwrap_or(false);
+        if !valid {
+            return Err(ContractError::InvalidPermit {});
+        }
+
+        let info = MessageInfo {
+            sender: owner.clone(),
+            funds: vec![],
+        };
+        let res = match token_id {
+            Some(token_id) => self.approve(deps.branch(), env, info, spender, token_id, expires)?,
+            None => self.approve_all(deps.branch(), env, info, spender, expires)?,
+        };
+        self.permit_nonces
+            .save(deps.storage, &owner, &(nonce + 1))?;
+
+        Ok(res.add_attribute("permit_nonce", nonce.to_string()))
+    }
+
+    /// `limit` bounds the token ids and operators looked at in the whole call
+    pub fn prune_expired(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        token_ids: Vec<String>,
+        owners: Vec<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Response<C>, ContractError> {
+        let mut remaining = limit.unwrap_or(DEFAULT_PRUNE_LIMIT).min(MAX_PRUNE_LIMIT) as usize;
+        let mut start_after = start_after
+            .map(|operator| deps.api.addr_validate(&operator))
+            .transpose()?;
+
+        let mut pruned_approvals = 0;
+        for token_id in token_ids.iter().take(remaining) {
+            remaining -= 1;
+            // burned or unknown tokens have nothing to prune
+            let mut token = match self.tokens.may_load(deps.storage, token_id)? {
+                Some(token) => token,
+                None => continue,
+            };
+            let before = token.approvals.len();
+            token.approvals.retain(|apr| !apr.is_expired(&env.block));
+            if token.approvals.len() != before {
+                pruned_approvals += before - token.approvals.len();
+                self.tokens.save(deps.storage, token_id, &token)?;
+            }
+        }
+
+        let mut pruned_operators = 0;
+        let mut next = None;
+        for owner in &owners {
+            if remaining == 0 {
+                break;
+            }
+            let owner = deps.api.addr_validate(owner)?;
+            let (scanned, pruned) = self.prune_operators(
+                deps.storage,
+                &env.block,
+                &owner,
+                start_after.take().as_ref(),
+                remaining,
+            )?;
+            pruned_operators += pruned;
+            remaining -= scanned.len();
+            if remaining == 0 {
+                next = scanned.last().map(|operator| (owner, operator.clone()));
+            }
+        }
+
+        let mut res = Response::new()
+            .add_attribute("action", "prune_expired")
+            .add_attribute("pruned_approvals", pruned_approvals.to_string())
+            .add_attribute("pruned_operators", pruned_operators.to_string());
+        if let Some((owner, operator)) = next {
+            res = res
+                .add_attribute("next_owner", owner)
+                .add_attribute("next_start_after", operator);
+        }
+        Ok(res)
+    }
+
+    pub fn update_operator_filter(
+        &self,
+        deps: DepsMut,
+        sender: &Addr,
+        add: Vec<String>,
+        remove: Vec<String>,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, sender)?;
+
+        for operator in &add {
+            let operator = deps.api.addr_validate(operator)?;
+            self.filtered_operators
+                .save(deps.storage, &operator, &Empty {})?;
+        }
+        for operator in &remove {
+            let operator = deps.api.addr_validate(operator)?;
+            self.filtered_operators.remove(deps.storage, &operator);
+        }
+        Ok(Response::new()
+            .add_attribute("action", "update_operator_filter")
+            .add_attribute("added", add.join(","))
+            .add_attribute("removed", remove.join(",")))
+    }
+
+    pub fn toggle_sale_active(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        sender: &Addr,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, sender)?;
+
+        let mut config = self.config.load(deps.storage)?;
+        if config.sale_paused {
+            config.sale_paused = false;
+        } else if config.sale_time == u64::MAX {
+            config.sale_time = env.block.time.seconds();
+            assert_sale_window(&config)?;
+        } else {
+            config.sale_paused = true;
+        }
+        self.config.save(deps.storage, &config)?;
+
+        let sale_state = self.sale_state.load(deps.storage)?;
+        let status = config.sale_status(&sale_state, env.block.time.seconds());
+        Ok(Response::new()
+            .add_attribute("action", "toggle_sale_active")
+            .add_attribute("sale_paused", config.sale_paused.to_string())
+            .add_attribute("sale_active", (status == SaleStatus::Active).to_string()))
+    }
+
+    pub fn add_recipe(
+        &self,
+        deps: DepsMut,
+        sender: &Addr,
+        recipe: Recipe<T>,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, sender)?;
+
+        if recipe.inputs.iter().all(|input| input.count == 0) {
+            return Err(ContractError::InvalidRecipe {});
+        }
+
+        let recipe_id = self.increment_recipes(deps.storage)?;
+        self.recipes.save(deps.storage, recipe_id, &recipe)?;
+        Ok(Response::new()
+            .add_attribute("action", "add_recipe")
+            .add_attribute("recipe_id", recipe_id.to_string()))
+    }
+
+    pub fn remove_recipe(
+        &self,
+        deps: DepsMut,
+        sender: &Addr,
+        recipe_id: u64,
+    ) -> Result<Response<C>, ContractError> {
+        cw_ownable::assert_owner(deps.storage, sender)?;
+
+        // make sure the recipe exists
+        self.recipes.load(deps.storage, recipe_id)?;
+        self.recipes.remove(deps.storage, recipe_id);
+        Ok(Response::new()
+            .add_attribute("action", "remove_recipe")
+            .add_attribute("recipe_id", recipe_id.to_string()))
+    }
+
+    pub fn redeem(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        recipe_id: u64,
+        token_ids: Vec<String>,
+    ) -> Result<Response<C>, ContractError> {
+        self.assert_not_blocklisted(deps.storage, &info.sender)?;
+        let mut recipe = self.recipes.load(deps.storage, recipe_id)?;
+        if let Some(supply_cap) = recipe.supply_cap {
+            if recipe.redeemed >= supply_cap {
+                return Err(ContractError::RecipeSoldOut { recipe_id });
+            }
+        }
+
+        // assign every token to an input, preferring inputs that filter on the
+        // extension so that unfiltered inputs are left for the remaining tokens
+        let filters = recipe
+            .inputs
+            .iter()
+            .map(|input| input.extension.as_ref().map(to_json_vec).transpose())
+            .collect::<StdResult<Vec<_>>>()?;
+        let mut missing: Vec<u64> = recipe.inputs.iter().map(|input| input.count).collect();
+        for token_id in &token_ids {
+            let token = self.tokens.load(deps.storage, token_id)?;
+            let extension = to_json_vec(&token.extension)?;
+            let slot = (0..missing.len())
+                .find(|&i| missing[i] > 0 && filters[i].as_ref() == Some(&extension))
+                .or_else(|| (0..missing.len()).find(|&i| missing[i] > 0 && filters[i].is_none()));
+            match slot {
+                Some(i) => missing[i] -= 1,
+                None => return Err(ContractError::RecipeMismatch { recipe_id }),
+            }
+        }
+        if missing.iter().any(|count| *count > 0) {
+            return Err(ContractError::RecipeMismatch { recipe_id });
+        }
+
+        // burn the inputs, this checks the sender is allowed to burn every one of them
+        for token_id in &token_ids {
+            self.burn(deps.branch(), env.clone(), info.clone(), token_id.clone())?;
+        }
+
+        let config = self.config.load(deps.storage)?;
+        let mut sale_state = self.sale_state.load(deps.storage)?;
+        if recipe.count_in_supply {
+            if sale_state.remaining_supply(&config) == 0 {
+                return Err(ContractError::SoldOut {});
+            }
+            sale_state.total_supply += 1;
+        }
+        sale_state.minted += 1;
+        self.sale_state.save(deps.storage, &sale_state)?;
+        let redeemed_supply = self.redeemed_supply.may_load(deps.storage)?.unwrap_or_default() + 1;
+        self.redeemed_supply.save(deps.storage, &redeemed_supply)?;
+        recipe.redeemed += 1;
+        self.recipes.save(deps.storage, recipe_id, &recipe)?;
+
+        let new_token_id = config
+            .token_id_scheme
+            .redemption_id(&config.token_id_base, redeemed_supply);
+        let token = TokenInfo {
+            owner: info.sender.clone(),
+            approvals: vec![],
+            token_uri: recipe.output_uri,
+            extension: recipe.output_extension,
+            index: None,
+        };
+        self.tokens
+            .update(deps.storage, &new_token_id, |old| match old {
+                Some(_) => Err(ContractError::Claimed {}),
+                None => Ok(token),
+            })?;
+        self.increment_tokens(deps.storage)?;
+        self.increment_balance(deps.storage, &info.sender)?;
+        let record = HistoryRecord::new(
+            HistoryAction::Mint,
+            &info.sender,
+            None,
+            Some(&info.sender),
+            &env.block,
+        );
+        self.push_history(deps.storage, &new_token_id, &record)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "redeem")
+            .add_attribute("recipe_id", recipe_id.to_string())
+            .add_attribute("sender", info.sender)
+            .add_attribute("burned", token_ids.len().to_string())
+            .add_attribute("token_id", new_token_id))
+    }
+}
+
+impl<'a, T, C, E, Q> Cw721Execute<T, C> for Cw721Contract<'a, T, C, E, Q>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    C: CustomMsg,
+    E: CustomMsg,
+    Q: CustomMsg,
+{
+    type Err = ContractError;
+
+    fn transfer_nft(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        recipient: String,
+        token_id: String,
+    ) -> Result<Response<C>, ContractError> {
+        self._transfer_nft(deps, &env, &info, &recipient, &token_id, HistoryAction::Transfer)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "transfer_nft")
+            .add_attribute("sender", info.sender)
+            .add_attribute("recipient", recipient)
+            .add_attribute("token_id", token_id))
+    }
+
+    fn send_nft(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        contract: String,
+        token_id: String,
+        msg: Binary,
+    ) -> Result<Response<C>, ContractError> {
+        // Transfer token
+        self._transfer_nft(deps, &env, &info, &contract, &token_id, HistoryAction::Send)?;
+
+        let send = Cw721ReceiveMsg {
+            sender: info.sender.to_string(),
+            token_id: token_id.clone(),
+            msg,
+        };
+
+        // Send message
+        Ok(Response::new()
+            .add_message(send.into_cosmos_msg(contract.clone())?)
+            .add_attribute("action", "send_nft")
+            .add_attribute("sender", info.sender)
+            .add_attribute("recipient", contract)
+            .add_attribute("token_id", token_id))
+    }
+
+    fn approve(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        spender: String,
+        token_id: String,
+        expires: Option<Expiration>,
+    ) -> Result<Response<C>, ContractError> {
+        self._update_approvals(deps, &env, &info, &spender, &token_id, true, expires)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "approve")
+            .add_attribute("sender", info.sender)
+            .add_attribute("spender", spender)
+            .add_attribute("token_id", token_id))
+    }
+
+    fn revoke(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        spender: String,
+        token_id: String,
+    ) -> Result<Response<C>, ContractError> {
+        self._update_approvals(deps, &env, &info, &spender, &token_id, false, None)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "revoke")
+            .add_attribute("sender", info.sender)
+            .add_attribute("spender", spender)
+            .add_attribute("token_id", token_id))
+    }
+
+    fn approve_all(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        operator: String,
+        expires: Option<Expiration>,
+    ) -> Result<Response<C>, ContractError> {
+        // reject expired data as invalid
+        let expires = expires.unwrap_or_default();
+        if expires.is_expired(&env.block) {
+            return Err(ContractError::Expired {});
+        }
+
+        // set the operator for us
+        let operator_addr = deps.api.addr_validate(&operator)?;
+        self.assert_operator_allowed(deps.storage, &operator_addr)?;
+        self.prune_operators(
+            deps.storage,
+            &env.block,
+            &info.sender,
+            None,
+            DEFAULT_PRUNE_LIMIT as usize,
+        )?;
+        self.operators
+            .save(deps.storage, (&info.sender, &operator_addr), &expires)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "approve_all")
+            .add_attribute("sender", info.sender)
+            .add_attribute("operator", operator))
+    }
+
+    fn revoke_all(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        operator: String,
+    ) -> Result<Response<C>, ContractError> {
+        let operator_addr = deps.api.addr_validate(&operator)?;
+        self.operators
+            .remove(deps.storage, (&info.sender, &operator_addr));
+
+        Ok(Response::new()
+            .add_attribute("action", "revoke_all")
+            .add_attribute("sender", info.sender)
+            .add_attribute("operator", operator))
+    }
+
+    fn burn(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        token_id: String,
+    ) -> Result<Response<C>, ContractError> {
+        let token = self.tokens.load(deps.storage, &token_id)?;
+        self.check_can_send(deps.as_ref(), &env, &info, &token)?;
+
+        self.tokens.remove(deps.storage, &token_id)?;
+        self.decrement_tokens(deps.storage)?;
+        self.decrement_balance(deps.storage, &token.owner)?;
+        self.sale_state.update(deps.storage, |mut sale_state| -> StdResult<_> {
+            sale_state.burned += 1;
+            Ok(sale_state)
+        })?;
+        let record = HistoryRecord::new(
+            HistoryAction::Burn,
+            &info.sender,
+            Some(&token.owner),
+            None,
+            &env.block,
+        );
+        self.push_history(deps.storage, &token_id, &record)?;
+
+        Ok(Response::new()
+            .add_attribute("action", "burn")
+            .add_attribute("sender", info.sender)
             .add_attribute("token_id", token_id))
     }
 }
@@ -739,6 +1942,258 @@ where
     E: CustomMsg,
     Q: CustomMsg,
 {
+    /// Validates `update` against the current config and sale state, then saves the result
+    pub fn apply_config_update(
+        &self,
+        deps: DepsMut,
+        update: ConfigUpdate,
+    ) -> Result<Config, ContractError> {
+        let mut config = self.config.load(deps.storage)?;
+        let sale_state = self.sale_state.load(deps.storage)?;
+        if let Some(base_uri) = update.base_uri {
+            config.base_uri = base_uri;
+        }
+        if let Some(uri_template) = update.uri_template {
+            config.uri_template = uri_template;
+        }
+        if let Some(uri_suffix) = update.uri_suffix {
+            config.uri_suffix = uri_suffix;
+        }
+        if let Some(token_id_base) = update.token_id_base {
+            config.token_id_base = token_id_base;
+        }
+        if let Some(address) = update.withdraw_address {
+            deps.api.addr_validate(&address)?;
+            config.withdraw_address = Some(address);
+        }
+        if let Some(address) = update.dev_wallet {
+            deps.api.addr_validate(&address)?;
+            config.dev_wallet = address;
+        }
+        if let Some(mint_per_tx) = update.mint_per_tx {
+            if mint_per_tx == 0 {
+                return Err(ContractError::ZeroMintPerTx {});
+            }
+            config.mint_per_tx = mint_per_tx;
+        }
+        if let Some(mint_fee) = update.mint_fee {
+            config.mint_fee = mint_fee;
+        }
+        if let Some(dev_fee) = update.dev_fee {
+            config.dev_fee = dev_fee;
+        }
+        if let Some(sale_time) = update.sale_time {
+            config.sale_time = sale_time;
+        }
+        if let Some(sale_end_time) = update.sale_end_time {
+            assert_escrow_end_time(&sale_state, &config, Some(sale_end_time))?;
+            config.sale_end_time = Some(sale_end_time);
+        }
+        assert_sale_window(&config)?;
+        if let Some(purchase_mode) = update.purchase_mode {
+            assert_purchase_mode(&purchase_mode)?;
+            config.purchase_mode = purchase_mode;
+        }
+        if let Some(max_per_block) = update.max_per_block {
+            config.max_per_block = Some(max_per_block).filter(|max| *max > 0);
+        }
+        if let Some(buy_cooldown) = update.buy_cooldown {
+            config.buy_cooldown = Some(buy_cooldown).filter(|cooldown| *cooldown > 0);
+        }
+        if let Some(operator_filter) = update.operator_filter {
+            config.operator_filter = operator_filter;
+        }
+        if let Some(burns_free_supply) = update.burns_free_supply {
+            config.burns_free_supply = burns_free_supply;
+        }
+        if let Some(supply_limit) = update.supply_limit {
+            config.supply_limit = supply_limit;
+        }
+        let used_supply = sale_state.used_supply(&config);
+        if config.supply_limit < used_supply {
+            return Err(ContractError::SupplyLimitTooLow {
+                supply_limit: config.supply_limit,
+                total_supply: used_supply,
+            });
+        }
+        self.config.save(deps.storage, &config)?;
+        Ok(config)
+    }
+
+    pub fn assert_sale_active(
+        &self,
+        storage: &dyn Storage,
+        config: &Config,
+        env: &Env,
+    ) -> Result<(), ContractError> {
+        let sale_state = self.sale_state.load(storage)?;
+        match config.sale_status(&sale_state, env.block.time.seconds()) {
+            SaleStatus::Active => Ok(()),
+            SaleStatus::NotStarted | SaleStatus::Paused => Err(ContractError::SaleUnactivate {}),
+            SaleStatus::Ended => Err(ContractError::SaleEnded {}),
+            SaleStatus::SoldOut => Err(ContractError::SoldOut {}),
+        }
+    }
+
+    /// Number of NFTs out of `qty` that can be sold in one transaction, bounded by
+    /// `mint_per_tx` and the remaining supply
+    pub fn purchasable(&self, storage: &dyn Storage, config: &Config, qty: u64) -> StdResult<u64> {
+        let sale_state = self.sale_state.load(storage)?;
+        Ok(qty
+            .min(config.mint_per_tx)
+            .min(sale_state.remaining_supply(config)))
+    }
+
+    /// Checks the buyer's cooldown and bounds `qty` by what is left of `max_per_block`
+    /// in the current block, then records the purchase against both limits
+    pub fn apply_rate_limits(
+        &self,
+        storage: &mut dyn Storage,
+        config: &Config,
+        env: &Env,
+        buyer: &Addr,
+        qty: u64,
+    ) -> Result<u64, ContractError> {
+        let now = env.block.time.seconds();
+        if let (Some(buy_cooldown), Some(last_buy)) =
+            (config.buy_cooldown, self.last_buy.may_load(storage, buyer)?)
+        {
+            let next_buy_time = last_buy + buy_cooldown;
+            if now < next_buy_time {
+                return Err(ContractError::BuyCooldown { next_buy_time });
+            }
+        }
+
+        let mut sale_state = self.sale_state.load(storage)?;
+        if sale_state.block_height != env.block.height {
+            sale_state.block_height = env.block.height;
+            sale_state.block_minted = 0;
+        }
+        let mut qty = qty;
+        if let Some(max_per_block) = config.max_per_block {
+            let left = max_per_block.saturating_sub(sale_state.block_minted);
+            if left == 0 && qty > 0 {
+                return Err(ContractError::BlockMintLimit { max_per_block });
+            }
+            qty = qty.min(left);
+        }
+
+        if config.buy_cooldown.is_some() {
+            self.last_buy.save(storage, buyer, &now)?;
+        }
+        if config.max_per_block.is_some() {
+            sale_state.block_minted += qty;
+            self.sale_state.save(storage, &sale_state)?;
+        }
+        Ok(qty)
+    }
+
+    pub fn assert_not_blocklisted(
+        &self,
+        storage: &dyn Storage,
+        address: &Addr,
+    ) -> Result<(), ContractError> {
+        if self.blocklist.has(storage, address) {
+            return Err(ContractError::Blocklisted {
+                address: address.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The blocklist is managed by the owner and the compliance address
+    fn assert_compliance(&self, storage: &dyn Storage, sender: &Addr) -> Result<(), ContractError> {
+        let compliance = self.config.load(storage)?.compliance;
+        if compliance.as_deref() == Some(sender.as_str()) {
+            return Ok(());
+        }
+        cw_ownable::assert_owner(storage, sender)?;
+        Ok(())
+    }
+
+    pub fn assert_operator_allowed(
+        &self,
+        storage: &dyn Storage,
+        operator: &Addr,
+    ) -> Result<(), ContractError> {
+        if !self.operator_allowed(storage, operator)? {
+            return Err(ContractError::OperatorNotAllowed {
+                operator: operator.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Looks at the first `limit` operators of `owner` after `start_after` and removes the
+    /// expired ones, returns the operators looked at and the number removed
+    pub fn prune_operators(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        owner: &Addr,
+        start_after: Option<&Addr>,
+        limit: usize,
+    ) -> StdResult<(Vec<Addr>, usize)> {
+        let scanned = self
+            .operators
+            .prefix(owner)
+            .range(storage, start_after.map(Bound::exclusive), None, Order::Ascending)
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+        let mut pruned = 0;
+        for (operator, expires) in &scanned {
+            if expires.is_expired(block) {
+                self.operators.remove(storage, (owner, operator));
+                pruned += 1;
+            }
+        }
+        Ok((scanned.into_iter().map(|(operator, _)| operator).collect(), pruned))
+    }
+
+    /// Returns the (withdraw address, dev wallet) pair sale proceeds are split between
+    pub fn fee_recipients(&self, config: &Config, buyer: &Addr) -> (String, String) {
+        let withdraw_address = config
+            .withdraw_address
+            .clone()
+            .unwrap_or_else(|| buyer.to_string());
+        (withdraw_address, config.dev_wallet.clone())
+    }
+
+    /// Pays the fees of a purchase to the withdraw address and dev wallet, or holds
+    /// them in escrow with the bought `token_ids` until the soft cap is reached
+    #[allow(clippy::too_many_arguments)]
+    pub fn pay_fees(
+        &self,
+        storage: &mut dyn Storage,
+        config: &Config,
+        buyer: &Addr,
+        token_ids: Vec<String>,
+        denom: &Denom,
+        mint_fee: Uint128,
+        dev_fee: Uint128,
+    ) -> StdResult<Vec<CosmosMsg<C>>> {
+        let (withdraw_address, dev_wallet) = self.fee_recipients(config, buyer);
+        if !self.sale_state.load(storage)?.escrow_active(config) {
+            return [(withdraw_address, mint_fee), (dev_wallet, dev_fee)]
+                .into_iter()
+                .filter(|(_, amount)| !amount.is_zero())
+                .map(|(recipient, amount)| payment_msg(denom, recipient, amount))
+                .collect();
+        }
+
+        // without a withdraw address the buyer keeps the mint fee, it is not escrowed
+        let mut msgs = vec![];
+        let mut escrowed_mint_fee = mint_fee;
+        if config.withdraw_address.is_none() {
+            if !mint_fee.is_zero() {
+                msgs.push(payment_msg(denom, withdraw_address, mint_fee)?);
+            }
+            escrowed_mint_fee = Uint128::zero();
+        }
+        self.record_escrow(storage, buyer, token_ids, denom, escrowed_mint_fee, dev_fee)?;
+        Ok(msgs)
+    }
+
     pub fn _transfer_nft(
         &self,
         deps: DepsMut,
@@ -746,12 +2201,28 @@ where
         info: &MessageInfo,
         recipient: &str,
         token_id: &str,
+        action: HistoryAction,
     ) -> Result<TokenInfo<T>, ContractError> {
         let mut token = self.tokens.load(deps.storage, token_id)?;
         // ensure we have permissions
         self.check_can_send(deps.as_ref(), env, info, &token)?;
+        if info.sender != token.owner {
+            self.assert_operator_allowed(deps.storage, &info.sender)?;
+        }
         // set owner and remove existing approvals
-        token.owner = deps.api.addr_validate(recipient)?;
+        let recipient = deps.api.addr_validate(recipient)?;
+        self.assert_not_blocklisted(deps.storage, &recipient)?;
+        self.decrement_balance(deps.storage, &token.owner)?;
+        self.increment_balance(deps.storage, &recipient)?;
+        let record = HistoryRecord::new(
+            action,
+            &info.sender,
+            Some(&token.owner),
+            Some(&recipient),
+            &env.block,
+        );
+        self.push_history(deps.storage, token_id, &record)?;
+        token.owner = recipient;
         token.approvals = vec![];
         self.tokens.save(deps.storage, token_id, &token)?;
         Ok(token)
@@ -773,12 +2244,16 @@ where
         // ensure we have permissions
         self.check_can_approve(deps.as_ref(), env, info, &token)?;
 
-        // update the approval list (remove any for the same spender before adding)
+        // update the approval list (remove any for the same spender before adding,
+        // along with the expired ones)
         let spender_addr = deps.api.addr_validate(spender)?;
-        token.approvals.retain(|apr| apr.spender != spender_addr);
+        token
+            .approvals
+            .retain(|apr| apr.spender != spender_addr && !apr.is_expired(&env.block));
 
         // only difference between approve and revoke
         if add {
+            self.assert_operator_allowed(deps.storage, &spender_addr)?;
             // reject expired data as invalid
             let expires = expires.unwrap_or_default();
             if expires.is_expired(&env.block) {
@@ -862,3 +2337,97 @@ where
         }
     }
 }
+
+fn cw20_transfer(token: &Addr, recipient: String, amount: Uint128) -> StdResult<WasmMsg> {
+    Ok(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_json_binary(&Cw20ExecuteMsg::Transfer { recipient, amount })?,
+        funds: vec![],
+    })
+}
+
+fn payment_msg<C>(denom: &Denom, recipient: String, amount: Uint128) -> StdResult<CosmosMsg<C>> {
+    Ok(match denom {
+        Denom::Native(denom) => BankMsg::Send {
+            to_address: recipient,
+            amount: vec![coin(amount.u128(), denom)],
+        }
+        .into(),
+        Denom::Cw20(token) => cw20_transfer(token, recipient, amount)?.into(),
+    })
+}
+
+/// Random number for the `i`-th NFT drawn with `seed`
+fn random_u64(seed: &[u8], i: u64) -> u64 {
+    let hash = Sha256::new()
+        .chain_update(seed)
+        .chain_update(i.to_be_bytes())
+        .finalize();
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// A revealed purchase has to wait at least one block and can't expire before it
+/// can be revealed
+fn assert_purchase_mode(purchase_mode: &PurchaseMode) -> Result<(), ContractError> {
+    match purchase_mode {
+        PurchaseMode::CommitReveal {
+            reveal_delay,
+            reveal_timeout,
+        } if *reveal_delay == 0 || reveal_timeout <= reveal_delay => {
+            Err(ContractError::InvalidRevealWindow {})
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Padding an id beyond the digits of any index only makes it longer
+fn assert_token_id_scheme(scheme: &TokenIdScheme) -> Result<(), ContractError> {
+    match scheme {
+        TokenIdScheme::ZeroPadded { width } if *width > MAX_ID_WIDTH => {
+            Err(ContractError::TokenIdWidthTooLarge {
+                width: *width,
+                max: MAX_ID_WIDTH,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Vouchers are checked against a compressed secp256k1 public key
+fn assert_voucher_signer(pubkey: &Binary) -> Result<(), ContractError> {
+    match pubkey.as_slice() {
+        [0x02 | 0x03, rest @ ..] if rest.len() == 32 => Ok(()),
+        _ => Err(ContractError::InvalidVoucherSigner {}),
+    }
+}
+
+/// Refunds open at the sale end time, so it can only be brought forward while
+/// proceeds are held in escrow
+fn assert_escrow_end_time(
+    sale_state: &SaleState,
+    config: &Config,
+    sale_end_time: Option<u64>,
+) -> Result<(), ContractError> {
+    if !sale_state.escrow_active(config) {
+        return Ok(());
+    }
+    match (config.sale_end_time, sale_end_time) {
+        (Some(current), Some(new)) if new <= current => Ok(()),
+        _ => Err(ContractError::EscrowEndTimeLocked {}),
+    }
+}
+
+/// A sale can't end before it starts
+fn assert_sale_window(config: &Config) -> Result<(), ContractError> {
+    match config.sale_end_time {
+        Some(sale_end_time) if sale_end_time <= config.sale_time && config.sale_time != u64::MAX => {
+            Err(ContractError::InvalidSaleWindow {
+                sale_time: config.sale_time,
+                sale_end_time,
+            })
+        }
+        _ => Ok(()),
+    }
+}
@@ -6,11 +6,13 @@ use cosmwasm_std::{
 };
 use cw721::{
     AllNftInfoResponse, Approval, ApprovalResponse, ApprovalsResponse, ContractInfoResponse,
-    NftInfoResponse, NumTokensResponse, OperatorsResponse, OwnerOfResponse, TokensResponse,
+    NftInfoResponse, NumTokensResponse, OperatorsResponse, OrderBy, OwnerOfResponse,
+    TokensResponse,
 };
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use crate::msg::BalanceResponse;
 use crate::{ExecuteMsg, QueryMsg};
 
 #[cw_serde]
@@ -94,19 +96,24 @@ impl<Q: CustomMsg, E: CustomMsg> Cw721Contract<Q, E> {
         Ok(res)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn all_operators<T: Into<String>>(
         &self,
         querier: &QuerierWrapper,
         owner: T,
         include_expired: bool,
         start_after: Option<String>,
+        start_before: Option<String>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
     ) -> StdResult<Vec<Approval>> {
         let req = QueryMsg::AllOperators {
             owner: owner.into(),
             include_expired: Some(include_expired),
             start_after,
+            start_before,
             limit,
+            order,
         };
         let res: OperatorsResponse = self.query(querier, req)?;
         Ok(res.operators)
@@ -118,6 +125,14 @@ impl<Q: CustomMsg, E: CustomMsg> Cw721Contract<Q, E> {
         Ok(res.count)
     }
 
+    pub fn balance<T: Into<String>>(&self, querier: &QuerierWrapper, owner: T) -> StdResult<u64> {
+        let req = QueryMsg::Balance {
+            owner: owner.into(),
+        };
+        let res: BalanceResponse = self.query(querier, req)?;
+        Ok(res.balance)
+    }
+
     /// With metadata extension
     pub fn contract_info(&self, querier: &QuerierWrapper) -> StdResult<ContractInfoResponse> {
         let req = QueryMsg::ContractInfo {};
@@ -156,12 +171,16 @@ impl<Q: CustomMsg, E: CustomMsg> Cw721Contract<Q, E> {
         querier: &QuerierWrapper,
         owner: T,
         start_after: Option<String>,
+        start_before: Option<String>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
     ) -> StdResult<TokensResponse> {
         let req = QueryMsg::Tokens {
             owner: owner.into(),
             start_after,
+            start_before,
             limit,
+            order,
         };
         self.query(querier, req)
     }
@@ -171,9 +190,16 @@ impl<Q: CustomMsg, E: CustomMsg> Cw721Contract<Q, E> {
         &self,
         querier: &QuerierWrapper,
         start_after: Option<String>,
+        start_before: Option<String>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
     ) -> StdResult<TokensResponse> {
-        let req = QueryMsg::AllTokens { start_after, limit };
+        let req = QueryMsg::AllTokens {
+            start_after,
+            start_before,
+            limit,
+            order,
+        };
         self.query(querier, req)
     }
 
@@ -184,6 +210,7 @@ impl<Q: CustomMsg, E: CustomMsg> Cw721Contract<Q, E> {
 
     /// returns true if the contract supports the enumerable extension
     pub fn has_enumerable(&self, querier: &QuerierWrapper) -> bool {
-        self.tokens(querier, self.addr(), None, Some(1)).is_ok()
+        self.tokens(querier, self.addr(), None, None, Some(1), None)
+            .is_ok()
     }
 }
@@ -12,7 +12,7 @@ mod contract_tests;
 mod multi_tests;
 
 pub use crate::error::ContractError;
-pub use crate::msg::{ExecuteMsg, InstantiateMsg, MinterResponse, QueryMsg};
+pub use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, MinterResponse, QueryMsg};
 pub use crate::state::Cw721Contract;
 
 // These types are re-exported so that contracts interacting with this
@@ -28,16 +28,13 @@ use cosmwasm_std::Empty;
 // This is a simple type to let us handle empty extensions
 pub type Extension = Option<Empty>;
 
+// Denom `mint_fee` and `dev_fee` are priced in, other denoms are priced through `denom_prices`
+pub const NATIVE_DENOM: &str = "unibi";
+
 // Version info for migration
 pub const CONTRACT_NAME: &str = "crates.io:cw721-dropspace";
 pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-// currently we only support migrating from 0.16.0. this is ok for now because
-// we have not released any 0.16.x where x != 0
-//
-// TODO: parse semvar so that any version 0.16.x can be migrated from
-pub const EXPECTED_FROM_VERSION: &str = "0.16.0";
-
 pub mod entry {
     use super::*;
 
@@ -77,22 +74,15 @@ pub mod entry {
     }
 
     #[cfg_attr(not(feature = "library"), entry_point)]
-    pub fn migrate(deps: DepsMut, _env: Env, _msg: Empty) -> Result<Response, ContractError> {
-        // make sure the correct contract is being upgraded, and it's being
-        // upgraded from the correct version.
-        cw2::assert_contract_version(deps.as_ref().storage, CONTRACT_NAME, EXPECTED_FROM_VERSION)?;
-
-        // update contract version
-        cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-
-        // perform the upgrade
-        upgrades::v0_17::migrate::<Extension, Empty, Empty, Empty>(deps)
+    pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+        upgrades::migrate::<Extension, Empty, Empty, Empty>(deps, msg)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{Addr, Order, StdResult};
     use cw2::ContractVersion;
 
     use super::*;
@@ -119,7 +109,21 @@ mod tests {
                 supply_limit: Some(10000u64),
                 reserved_amount: Some(0u64),
                 dev_wallet: None,
-                sale_time: None
+                sale_time: None,
+                history_depth: None,
+                burns_free_supply: None,
+                token_id_scheme: None,
+                uri_template: None,
+                uri_suffix: None,
+                max_reserved: None,
+                sale_end_time: None,
+                soft_cap: None,
+                purchase_mode: None,
+                max_per_block: None,
+                buy_cooldown: None,
+                operator_filter: None,
+                compliance: None,
+                voucher_signer: None,
             },
         )
         .unwrap();
@@ -161,7 +165,21 @@ mod tests {
                 supply_limit: Some(10000u64),
                 reserved_amount: Some(0u64),
                 dev_wallet: None,
-                sale_time: None
+                sale_time: None,
+                history_depth: None,
+                burns_free_supply: None,
+                token_id_scheme: None,
+                uri_template: None,
+                uri_suffix: None,
+                max_reserved: None,
+                sale_end_time: None,
+                soft_cap: None,
+                purchase_mode: None,
+                max_per_block: None,
+                buy_cooldown: None,
+                operator_filter: None,
+                compliance: None,
+                voucher_signer: None,
             },
         )
         .unwrap();
@@ -172,4 +190,188 @@ mod tests {
             .map(|a| a.into_string());
         assert_eq!(minter, Some("owner".to_string()));
     }
+
+    #[test]
+    fn migrating_between_versions() {
+        let mut deps = mock_dependencies();
+        entry::instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            InstantiateMsg {
+                name: "".into(),
+                symbol: "".into(),
+                base_uri: None,
+                token_id_base: None,
+                minter: None,
+                withdraw_address: None,
+                mint_per_tx: None,
+                mint_fee: None,
+                dev_fee: None,
+                supply_limit: None,
+                reserved_amount: None,
+                dev_wallet: None,
+                sale_time: None,
+                history_depth: None,
+                burns_free_supply: None,
+                token_id_scheme: None,
+                uri_template: None,
+                uri_suffix: None,
+                max_reserved: None,
+                sale_end_time: None,
+                soft_cap: None,
+                purchase_mode: None,
+                max_per_block: None,
+                buy_cooldown: None,
+                operator_filter: None,
+                compliance: None,
+                voucher_signer: None,
+            },
+        )
+        .unwrap();
+
+        // newer versions can't be downgraded
+        cw2::set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "1.0.0").unwrap();
+        let err = entry::migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Downgrade {
+                from: "1.0.0".into(),
+                to: CONTRACT_VERSION.into(),
+            }
+        );
+
+        // other contracts can't be migrated
+        cw2::set_contract_version(deps.as_mut().storage, "crates.io:other", "0.18.0").unwrap();
+        entry::migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap_err();
+
+        // 0.18 only runs the 0.19 upgrade, which moves the settings into `Config`
+        let tract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+        let storage = deps.as_mut().storage;
+        tract.config.remove(storage);
+        tract.sale_state.remove(storage);
+        upgrades::v0_18::BASE_URI.save(storage, &"ipfs://".into()).unwrap();
+        upgrades::v0_18::TOKEN_ID_BASE.save(storage, &"Item".into()).unwrap();
+        upgrades::v0_18::DEV_WALLET.save(storage, &"dev".into()).unwrap();
+        upgrades::v0_18::MINT_PER_TX.save(storage, &2).unwrap();
+        upgrades::v0_18::MINT_FEE.save(storage, &100).unwrap();
+        upgrades::v0_18::DEV_FEE.save(storage, &10).unwrap();
+        upgrades::v0_18::SUPPLY_LIMIT.save(storage, &500).unwrap();
+        upgrades::v0_18::SALE_TIME.save(storage, &0).unwrap();
+        upgrades::v0_18::TOTAL_SUPPLY.save(storage, &0).unwrap();
+        upgrades::v0_18::RESERVED_AMOUNT.save(storage, &5).unwrap();
+        let token = state::TokenInfo {
+            owner: Addr::unchecked("owner"),
+            approvals: vec![],
+            token_uri: Some("\"ipfs://\"/3".into()),
+            extension: None,
+            index: None,
+        };
+        tract.tokens.save(storage, "Item #3", &token).unwrap();
+        cw2::set_contract_version(storage, CONTRACT_NAME, "0.18.0").unwrap();
+        let res = entry::migrate(
+            deps.as_mut(),
+            mock_env(),
+            MigrateMsg {
+                history_depth: Some(5),
+                ..MigrateMsg::default()
+            },
+        )
+        .unwrap();
+        assert!(res
+            .attributes
+            .iter()
+            .all(|attr| attr.value != "0.17.0"));
+        let version = cw2::get_contract_version(deps.as_ref().storage).unwrap();
+        assert_eq!(version.version, CONTRACT_VERSION);
+        assert_eq!(tract.history_depth.load(deps.as_ref().storage).unwrap(), 5);
+        assert_eq!(
+            tract.config.load(deps.as_ref().storage).unwrap(),
+            state::Config {
+                base_uri: "ipfs://".into(),
+                uri_template: state::DEFAULT_URI_TEMPLATE.into(),
+                uri_suffix: "".into(),
+                token_id_base: "Item".into(),
+                token_id_scheme: state::TokenIdScheme::Hash,
+                withdraw_address: None,
+                dev_wallet: "dev".into(),
+                mint_per_tx: 2,
+                mint_fee: 100,
+                dev_fee: 10,
+                supply_limit: 500,
+                sale_time: 0,
+                burns_free_supply: false,
+                max_reserved: None,
+                sale_end_time: None,
+                soft_cap: None,
+                purchase_mode: state::PurchaseMode::Direct,
+                max_per_block: None,
+                buy_cooldown: None,
+                operator_filter: state::OperatorFilter::Off,
+                compliance: None,
+                voucher_signer: None,
+                sale_paused: false,
+            }
+        );
+        assert_eq!(
+            tract.sale_state.load(deps.as_ref().storage).unwrap().reserved_amount,
+            5
+        );
+        let token = tract.tokens.load(deps.as_ref().storage, "Item #3").unwrap();
+        assert_eq!(token.index, Some(3));
+        assert_eq!(token.token_uri, Some("ipfs://3".into()));
+        assert_eq!(
+            tract.token_by_index(deps.as_ref(), 3).unwrap().token_id,
+            "Item #3"
+        );
+        assert!(upgrades::v0_18::MINT_FEE
+            .may_load(deps.as_ref().storage)
+            .unwrap()
+            .is_none());
+        // the balances are counted from the tokens
+        let owner = Addr::unchecked("owner");
+        assert_eq!(tract.balances.load(deps.as_ref().storage, &owner).unwrap(), 1);
+        assert_eq!(tract.num_holders.load(deps.as_ref().storage).unwrap(), 1);
+
+        // cw721-base 0.18 has no sale settings, they start from the instantiate defaults
+        let legacy = cw721_base_016::Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+        let storage = deps.as_mut().storage;
+        tract.config.remove(storage);
+        tract.sale_state.remove(storage);
+        let token = cw721_base_016::state::TokenInfo {
+            owner: owner.clone(),
+            approvals: vec![],
+            token_uri: None,
+            extension: None,
+        };
+        legacy.tokens.save(storage, "Legacy", &token).unwrap();
+        cw2::set_contract_version(storage, upgrades::LEGACY_CONTRACT_NAME, "0.18.0").unwrap();
+        entry::migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+        // the tokens are indexed under the new owner index only
+        let legacy_owned = legacy
+            .tokens
+            .idx
+            .owner
+            .prefix(owner.clone())
+            .keys(deps.as_ref().storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<String>>>()
+            .unwrap();
+        assert!(legacy_owned.is_empty());
+        let owned_tokens = tract
+            .tokens
+            .idx
+            .owner
+            .prefix(owner.clone())
+            .keys(deps.as_ref().storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<String>>>()
+            .unwrap();
+        assert_eq!(owned_tokens, vec!["Item #3".to_string(), "Legacy".to_string()]);
+        assert_eq!(tract.balances.load(deps.as_ref().storage, &owner).unwrap(), 2);
+        let config = tract.config.load(deps.as_ref().storage).unwrap();
+        assert_eq!(config.supply_limit, 100000);
+        assert_eq!(config.dev_wallet, "owner");
+        assert_eq!(config.sale_time, u64::MAX);
+        let sale_state = tract.sale_state.load(deps.as_ref().storage).unwrap();
+        assert_eq!(sale_state.mint_cursor, sale_state.total_supply);
+    }
 }
@@ -1,10 +1,16 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Binary, Coin};
-use cw721::Expiration;
+use cosmwasm_std::{Binary, Coin, Uint128};
+use cw20::Cw20ReceiveMsg;
+use cw721::{AllNftInfoResponse, Expiration, NftInfoResponse, OrderBy, OwnerOfResponse};
 use cw_ownable::{cw_ownable_execute, cw_ownable_query};
 use schemars::JsonSchema;
 use serde::{Serialize, Deserialize};
 
+use crate::state::{
+    BuyerStats, Commitment, Config, Escrow, EscrowedPayment, HistoryRecord, Recipe, RecipeInput, SaleStats,
+    OperatorFilter, PurchaseMode, SaleStatus, TokenIdScheme,
+};
+
 #[cw_serde]
 pub struct InstantiateMsg {
     /// Name of the NFT contract
@@ -27,6 +33,94 @@ pub struct InstantiateMsg {
     pub reserved_amount: Option<u64>,
     pub dev_wallet: Option<String>,
     pub sale_time: Option<u64>,
+    /// Sale end in seconds since epoch
+    pub sale_end_time: Option<u64>,
+    /// Maximum number of history records kept per token, unlimited if unset or 0
+    pub history_depth: Option<u64>,
+    /// If true, burned NFTs no longer count towards `supply_limit`, false by default
+    pub burns_free_supply: Option<bool>,
+    /// How token ids are built, `"{token_id_base} #{index}"` by default. A `ZeroPadded`
+    /// width can be at most 20, the digits of the largest index.
+    pub token_id_scheme: Option<TokenIdScheme>,
+    /// Token URI of minted NFTs, `"{base_uri}{index}{suffix}"` by default
+    pub uri_template: Option<String>,
+    /// Replaces `{suffix}` in the URI template, empty by default
+    pub uri_suffix: Option<String>,
+    /// Maximum number of NFTs the owner can reserve, can't be changed later.
+    /// Only bound by `supply_limit` if unset.
+    pub max_reserved: Option<u64>,
+    /// Minimum number of NFTs to sell, can't be changed later. Sale proceeds are held
+    /// in escrow until it is reached, and buyers are refunded if the sale ends before.
+    pub soft_cap: Option<u64>,
+    /// `Direct` by default
+    pub purchase_mode: Option<PurchaseMode>,
+    /// Maximum number of NFTs bought in one block, unlimited if unset or 0
+    pub max_per_block: Option<u64>,
+    /// Seconds an address has to wait between two purchases, none if unset or 0
+    pub buy_cooldown: Option<u64>,
+    /// `Off` by default
+    pub operator_filter: Option<OperatorFilter>,
+    /// Manages the blocklist along with the owner
+    pub compliance: Option<String>,
+    /// Compressed secp256k1 public key signing vouchers, vouchers are disabled if unset
+    pub voucher_signer: Option<Binary>,
+}
+
+/// Sale settings to change, unset fields are left as they are
+#[cw_serde]
+#[derive(Default)]
+pub struct ConfigUpdate {
+    pub base_uri: Option<String>,
+    /// Only applies to NFTs minted afterwards
+    pub uri_template: Option<String>,
+    pub uri_suffix: Option<String>,
+    pub token_id_base: Option<String>,
+    pub withdraw_address: Option<String>,
+    pub dev_wallet: Option<String>,
+    /// Must be greater than zero
+    pub mint_per_tx: Option<u64>,
+    pub mint_fee: Option<u64>,
+    pub dev_fee: Option<u64>,
+    /// Can't be lower than the number of NFTs counted against it
+    pub supply_limit: Option<u64>,
+    pub sale_time: Option<u64>,
+    /// Sale end in seconds since epoch
+    pub sale_end_time: Option<u64>,
+    pub burns_free_supply: Option<bool>,
+    /// Pending commitments keep their reveal window
+    pub purchase_mode: Option<PurchaseMode>,
+    /// 0 removes the limit
+    pub max_per_block: Option<u64>,
+    /// 0 removes the cooldown
+    pub buy_cooldown: Option<u64>,
+    /// Existing approvals are kept, but filtered operators can no longer transfer
+    pub operator_filter: Option<OperatorFilter>,
+}
+
+/// Settings applied after the upgrade steps have run, unset fields are left as they are.
+/// `{}` migrates without changing any setting.
+#[cw_serde]
+#[derive(Default)]
+pub struct MigrateMsg {
+    pub base_uri: Option<String>,
+    pub uri_template: Option<String>,
+    pub uri_suffix: Option<String>,
+    pub token_id_base: Option<String>,
+    pub withdraw_address: Option<String>,
+    pub mint_per_tx: Option<u64>,
+    pub mint_fee: Option<u64>,
+    pub dev_fee: Option<u64>,
+    pub supply_limit: Option<u64>,
+    pub dev_wallet: Option<String>,
+    pub sale_time: Option<u64>,
+    /// Sale end in seconds since epoch
+    pub sale_end_time: Option<u64>,
+    pub burns_free_supply: Option<bool>,
+    pub purchase_mode: Option<PurchaseMode>,
+    pub max_per_block: Option<u64>,
+    pub buy_cooldown: Option<u64>,
+    pub operator_filter: Option<OperatorFilter>,
+    pub history_depth: Option<u64>,
 }
 
 /// This is like Cw721ExecuteMsg but we add a Mint command for an owner
@@ -34,6 +128,7 @@ pub struct InstantiateMsg {
 /// use other control logic in any contract that inherits this.
 #[cw_ownable_execute]
 #[cw_serde]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg<T, E> {
     /// Transfer is a base message to move a token to another account without triggering actions
     TransferNft {
@@ -69,6 +164,17 @@ pub enum ExecuteMsg<T, E> {
     RevokeAll {
         operator: String,
     },
+    /// Approves `spender` on behalf of the owner of `owner_pubkey`, who signed the
+    /// `PermitPayload` off-chain. Approves `token_id`, or all tokens when `None`.
+    /// `nonce` must match the `PermitNonce` of the owner. Anyone can submit this.
+    Permit {
+        owner_pubkey: Binary,
+        spender: String,
+        token_id: Option<String>,
+        expires: Option<Expiration>,
+        nonce: u64,
+        signature: Binary,
+    },
 
     /// Mint a new NFT, can only be called by the contract minter
     Mint {
@@ -100,11 +206,11 @@ pub enum ExecuteMsg<T, E> {
     },
     /// Removes the withdraw address, so fees are sent to the contract. Only owner can call this.
     RemoveWithdrawAddress {},
-    /// Withdraw from the contract to the given address. Anyone can call this,
+    /// Withdraw from the contract to the withdraw address, except the funds held in escrow
+    /// or for pending commitments. Anyone can call this.
     WithdrawFunds {
         amount: Coin,
     },
-    /// which is okay since withdraw address has been set by owner.
     SetDevWallet {
         address: String,
     },
@@ -141,17 +247,179 @@ pub enum ExecuteMsg<T, E> {
         sale_time: u64,
     },
 
+    /// Sets when the sale closes, the sale runs until sold out if unset.
+    /// Only owner can call this.
+    SetSaleEndTime {
+        sale_end_time: Option<u64>,
+    },
+
+    /// Changes several sale settings at once, either all of them or none are applied.
+    /// Only owner can call this.
+    UpdateConfig(ConfigUpdate),
+
     Buy {
         qty: u64,
         extension: T,
     },
 
+    /// Buys with a voucher the voucher signer issued to the sender, see `VoucherPayload`.
+    /// `price` per NFT replaces the configured price, the dev fee of its denom is paid
+    /// out of it. Every voucher can be used once.
+    BuyWithVoucher {
+        qty: u64,
+        max_qty: u64,
+        price: Coin,
+        expires: u64,
+        nonce: u64,
+        signature: Binary,
+        extension: T,
+    },
+
+    /// Rotates the key signing vouchers, unset disables vouchers.
+    /// Only owner can call this.
+    SetVoucherSigner {
+        pubkey: Option<Binary>,
+    },
+
+    /// Sets the address managing the blocklist along with the owner.
+    /// Only owner can call this.
+    SetCompliance {
+        address: Option<String>,
+    },
+
+    /// Keeps addresses from buying and receiving NFTs.
+    /// Only owner and the compliance address can call this.
+    AddToBlocklist {
+        addresses: Vec<String>,
+    },
+
+    /// Only owner and the compliance address can call this.
+    RemoveFromBlocklist {
+        addresses: Vec<String>,
+    },
+
+    /// Drops expired approvals of `token_ids` and expired operators granted by `owners`,
+    /// looking at no more than `limit` token ids and operators in total. If the limit is
+    /// reached, the `next_owner` and `next_start_after` attributes tell where to continue.
+    /// Anyone can call this.
+    PruneExpired {
+        token_ids: Vec<String>,
+        owners: Vec<String>,
+        /// Operator of the first owner to continue after
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Adds and removes operators of the filter list, the list applies according to
+    /// `operator_filter`. Only owner can call this.
+    UpdateOperatorFilter {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+
+    /// Pays for a purchase kept hidden until `RevealBuy`, see `Commitment::hash`.
+    /// Only available in the `CommitReveal` purchase mode.
+    CommitBuy {
+        hash: Binary,
+    },
+
+    /// Mints `qty` random NFTs for the sender's commitment, from `reveal_delay` blocks
+    /// after it was made. Unused payment is refunded.
+    RevealBuy {
+        qty: u64,
+        salt: Binary,
+        extension: T,
+    },
+
+    /// Refunds the sender's commitment once it can no longer be revealed
+    RefundCommitment {},
+
+    /// Mints up to `qty` NFTs for free, bounded by `mint_per_tx`, the remaining supply
+    /// and `max_reserved`. NFTs go to `recipient`, the owner if unset.
+    /// Only owner can call this.
     Reserve {
         qty: u64,
         extension: T,
+        recipient: Option<String>,
     },
 
+    /// Pauses or resumes the sale, keeping its schedule. Opens the sale right away
+    /// if no `sale_time` was ever set. Only owner can call this.
     ToggleSaleActive {},
+
+    /// Burns the NFTs bought by the sender and refunds what they paid, once the sale
+    /// has ended below its soft cap. Only the NFTs the sender still holds are burned and
+    /// refunded pro rata, the others stay in escrow.
+    ClaimRefund {},
+
+    /// Pays the escrowed proceeds out once the soft cap is reached.
+    /// Only owner can call this.
+    ReleaseFunds {},
+
+    /// Adds a burn-to-mint redemption recipe. Only owner can call this.
+    AddRecipe {
+        /// Tokens that have to be burned to redeem the recipe
+        inputs: Vec<RecipeInput<T>>,
+        /// Universal resource identifier for the redeemed NFT
+        output_uri: Option<String>,
+        /// Extension of the redeemed NFT
+        output_extension: T,
+        /// Maximum number of redemptions, unlimited if unset
+        supply_cap: Option<u64>,
+        /// If true, redeemed NFTs count towards `total_supply` and `supply_limit`
+        count_in_supply: bool,
+    },
+
+    /// Removes a redemption recipe. Only owner can call this.
+    RemoveRecipe {
+        recipe_id: u64,
+    },
+
+    /// Burns `token_ids` (the sender must be allowed to burn each of them) and
+    /// mints the output of the recipe to the sender
+    Redeem {
+        recipe_id: u64,
+        token_ids: Vec<String>,
+    },
+
+    /// Buy with a CW20 token, the wrapped message must be a `ReceiveMsg`
+    Receive(Cw20ReceiveMsg),
+
+    /// Accepts `token` as payment at the given price. Only owner can call this.
+    SetCw20Price {
+        token: String,
+        mint_fee: Uint128,
+        dev_fee: Uint128,
+    },
+
+    /// Stops accepting `token` as payment. Only owner can call this.
+    RemoveCw20Price {
+        token: String,
+    },
+
+    /// Accepts the native `denom` as payment at the given price. Only owner can call this.
+    SetDenomPrice {
+        denom: String,
+        mint_fee: Uint128,
+        dev_fee: Uint128,
+    },
+
+    /// Stops accepting the native `denom` as payment. Only owner can call this.
+    RemoveDenomPrice {
+        denom: String,
+    },
+
+    /// Caps the number of history records kept per token, unset or 0 for no cap.
+    /// Only owner can call this.
+    SetHistoryDepth {
+        depth: Option<u64>,
+    },
+}
+
+/// Messages that can be sent to this contract through `Cw20ExecuteMsg::Send`
+#[cw_serde]
+pub enum ReceiveMsg<T> {
+    Buy { qty: u64, extension: T },
 }
 
 #[cw_ownable_query]
@@ -192,7 +460,11 @@ pub enum QueryMsg<Q: JsonSchema> {
         /// unset or false will filter out expired items, you must set to true to see them
         include_expired: Option<bool>,
         start_after: Option<String>,
+        /// Only lists items before this one, along with `order` pages in reverse
+        start_before: Option<String>,
         limit: Option<u32>,
+        /// Ascending if unset
+        order: Option<OrderBy>,
     },
     /// Total number of tokens issued
     #[returns(cw721::NumTokensResponse)]
@@ -223,15 +495,61 @@ pub enum QueryMsg<Q: JsonSchema> {
     Tokens {
         owner: String,
         start_after: Option<String>,
+        /// Only lists tokens before this one, along with `order` pages in reverse
+        start_before: Option<String>,
         limit: Option<u32>,
+        /// Ascending if unset
+        order: Option<OrderBy>,
     },
     /// With Enumerable extension.
     /// Requires pagination. Lists all token_ids controlled by the contract.
     #[returns(cw721::TokensResponse)]
     AllTokens {
+        start_after: Option<String>,
+        /// Only lists tokens before this one, along with `order` pages in reverse
+        start_before: Option<String>,
+        limit: Option<u32>,
+        /// Ascending if unset
+        order: Option<OrderBy>,
+    },
+    /// `NftInfo` of up to 1000 tokens at once, error if any token does not exist
+    #[returns(NftInfoBatchResponse<Q>)]
+    NftInfoBatch { token_ids: Vec<String> },
+    /// `OwnerOf` of up to 1000 tokens at once, error if any token does not exist
+    #[returns(OwnerOfBatchResponse)]
+    OwnerOfBatch {
+        token_ids: Vec<String>,
+        /// unset or false will filter out expired approvals, you must set to true to see them
+        include_expired: Option<bool>,
+    },
+    /// Requires pagination. Lists the `AllNftInfo` of all tokens, expired approvals
+    /// filtered out.
+    #[returns(AllTokensInfoResponse<Q>)]
+    AllTokensInfo {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    /// Like `Tokens`, ordered by mint index instead of token id. Redeemed NFTs, which
+    /// have no mint index, come last. Ascending if `order` is unset.
+    #[returns(cw721::TokensResponse)]
+    TokensByIndex {
+        owner: String,
+        start_after: Option<String>,
+        /// Only lists tokens before this one, along with `order` pages in reverse
+        start_before: Option<String>,
+        limit: Option<u32>,
+        order: Option<OrderBy>,
+    },
+    /// Like `AllTokens`, ordered by mint index instead of token id. Redeemed NFTs, which
+    /// have no mint index, come last. Ascending if `order` is unset.
+    #[returns(cw721::TokensResponse)]
+    AllTokensByIndex {
+        start_after: Option<String>,
+        /// Only lists tokens before this one, along with `order` pages in reverse
+        start_before: Option<String>,
+        limit: Option<u32>,
+        order: Option<OrderBy>,
+    },
 
     /// Return the minter
     #[returns(MinterResponse)]
@@ -257,7 +575,8 @@ pub enum QueryMsg<Q: JsonSchema> {
     #[returns(u64)]
     GetMintPerTx {},
 
-    #[returns(u64)]
+    /// Price of one NFT in every accepted native denom
+    #[returns(Vec<Coin>)]
     GetMintPrice {},
 
     #[returns(u64)]
@@ -275,14 +594,121 @@ pub enum QueryMsg<Q: JsonSchema> {
     #[returns(u64)]
     GetSaleTime {},
 
-    #[returns(bool)]
+    /// Status of the sale with its schedule
+    #[returns(SaleStatusResponse)]
     GetSaleStatus {},
 
-    #[returns(u64)]
+    /// Number of NFTs reserved so far and how many can still be reserved
+    #[returns(ReservedAmountResponse)]
     GetReservedAmount {},
 
+    /// Number of NFTs reserved for `address`
+    #[returns(u64)]
+    GetReservedFor { address: String },
+
+    /// All sale settings
+    #[returns(Config)]
+    GetConfig {},
+
+    /// Token id of the NFT minted at the given position of the mint sequence
+    #[returns(TokenByIndexResponse)]
+    TokenByIndex { index: u64 },
+
+    /// Minted, burned and circulating NFTs, and how many can still be minted
+    #[returns(SupplyInfoResponse)]
+    SupplyInfo {},
+
     #[returns(StatesResponse)]
     GetStates {},
+
+    /// NFTs bought by `address` and their payments held in escrow
+    #[returns(Escrow)]
+    Escrow { address: String },
+
+    /// Addresses that can't buy or receive NFTs
+    #[returns(BlocklistResponse)]
+    Blocklist {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Filter mode and the operators of the filter list
+    #[returns(OperatorFilterResponse)]
+    OperatorFilter {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Whether `operator` can be approved and transfer NFTs for their owners
+    #[returns(bool)]
+    OperatorAllowed { operator: String },
+
+    /// Pending purchase of `address` in the `CommitReveal` purchase mode
+    #[returns(Option<Commitment>)]
+    Commitment { address: String },
+
+    /// Whether the voucher with `nonce` was already used
+    #[returns(bool)]
+    VoucherUsed { nonce: u64 },
+
+    /// Nonce the next `Permit` of `owner` must be signed with
+    #[returns(u64)]
+    PermitNonce { owner: String },
+
+    /// Soft cap progress and the proceeds held in escrow
+    #[returns(SoftCapInfoResponse)]
+    SoftCapInfo {},
+
+    /// Number of NFTs minted through redemptions
+    #[returns(u64)]
+    GetRedeemedSupply {},
+
+    #[returns(RecipeResponse<Q>)]
+    Recipe { recipe_id: u64 },
+
+    #[returns(RecipesResponse<Q>)]
+    Recipes {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// List the accepted CW20 tokens and their prices
+    #[returns(Cw20PricesResponse)]
+    GetCw20Prices {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Number of NFTs held by the given address
+    #[returns(BalanceResponse)]
+    Balance { owner: String },
+
+    /// Lists every address holding at least one NFT together with its balance
+    #[returns(HoldersResponse)]
+    Holders {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    /// Number of addresses holding at least one NFT
+    #[returns(NumHoldersResponse)]
+    NumHolders {},
+
+    /// Ownership history of a token, oldest first. Burned tokens keep their history.
+    #[returns(TokenHistoryResponse)]
+    TokenHistory {
+        token_id: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    /// Revenue, fees and number of NFTs sold and reserved so far
+    #[returns(SaleStats)]
+    SaleStats {},
+
+    /// Purchases of the given address
+    #[returns(BuyerStats)]
+    BuyerStats { address: String },
 }
 
 /// Shows who can mint these tokens
@@ -291,6 +717,58 @@ pub struct MinterResponse {
     pub minter: Option<String>,
 }
 
+#[cw_serde]
+pub struct ReservedAmountResponse {
+    pub reserved: u64,
+    pub max_reserved: Option<u64>,
+    /// `None` if reserving is only bound by the supply limit
+    pub remaining: Option<u64>,
+}
+
+#[cw_serde]
+pub struct TokenByIndexResponse {
+    pub token_id: String,
+}
+
+#[cw_serde]
+pub struct BlocklistResponse {
+    pub addresses: Vec<String>,
+}
+
+#[cw_serde]
+pub struct OperatorFilterResponse {
+    pub filter: OperatorFilter,
+    pub operators: Vec<String>,
+}
+
+#[cw_serde]
+pub struct SoftCapInfoResponse {
+    pub soft_cap: Option<u64>,
+    pub sold: u64,
+    pub funds_released: bool,
+    pub escrowed: Vec<EscrowedPayment>,
+}
+
+#[cw_serde]
+pub struct SupplyInfoResponse {
+    /// NFTs ever minted, including redemptions
+    pub minted: u64,
+    /// NFTs ever burned
+    pub burned: u64,
+    /// NFTs currently in existence
+    pub circulating: u64,
+    /// NFTs counted against `supply_limit`
+    pub used_supply: u64,
+    pub supply_limit: u64,
+    /// NFTs that can still be bought or reserved
+    pub remaining: u64,
+    /// NFTs minted through `Reserve`
+    pub reserved: u64,
+    /// NFTs that can still be reserved, `None` if reserving is only bound by the supply limit
+    pub remaining_reserved: Option<u64>,
+    pub burns_free_supply: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct StatesResponse {
     pub name: String,
@@ -306,5 +784,103 @@ pub struct StatesResponse {
     pub dev_wallet: String,
     pub base_uri: String,
     pub sale_time: u64,
+    pub sale_end_time: Option<u64>,
     pub sale_active: bool,
+    pub max_per_block: Option<u64>,
+    pub buy_cooldown: Option<u64>,
+}
+
+#[cw_serde]
+pub struct SaleStatusResponse {
+    pub status: SaleStatus,
+    pub sale_time: u64,
+    pub sale_end_time: Option<u64>,
+}
+
+#[cw_serde]
+pub struct RecipeResponse<T> {
+    pub recipe_id: u64,
+    pub recipe: Recipe<T>,
+}
+
+#[cw_serde]
+pub struct RecipesResponse<T> {
+    pub recipes: Vec<RecipeResponse<T>>,
+}
+
+#[cw_serde]
+pub struct TokenNftInfo<T> {
+    pub token_id: String,
+    pub info: NftInfoResponse<T>,
+}
+
+#[cw_serde]
+pub struct NftInfoBatchResponse<T> {
+    pub tokens: Vec<TokenNftInfo<T>>,
+}
+
+#[cw_serde]
+pub struct TokenOwnerOf {
+    pub token_id: String,
+    pub access: OwnerOfResponse,
+}
+
+#[cw_serde]
+pub struct OwnerOfBatchResponse {
+    pub tokens: Vec<TokenOwnerOf>,
+}
+
+#[cw_serde]
+pub struct TokenAllNftInfo<T> {
+    pub token_id: String,
+    pub info: AllNftInfoResponse<T>,
+}
+
+#[cw_serde]
+pub struct AllTokensInfoResponse<T> {
+    pub tokens: Vec<TokenAllNftInfo<T>>,
+}
+
+#[cw_serde]
+pub struct BalanceResponse {
+    pub balance: u64,
+}
+
+#[cw_serde]
+pub struct Holder {
+    pub address: String,
+    pub count: u64,
+}
+
+#[cw_serde]
+pub struct HoldersResponse {
+    pub holders: Vec<Holder>,
+}
+
+#[cw_serde]
+pub struct NumHoldersResponse {
+    pub count: u64,
+}
+
+#[cw_serde]
+pub struct TokenHistoryEntry {
+    pub seq: u64,
+    pub record: HistoryRecord,
+}
+
+#[cw_serde]
+pub struct TokenHistoryResponse {
+    pub history: Vec<TokenHistoryEntry>,
+}
+
+#[cw_serde]
+pub struct Cw20PriceResponse {
+    pub token: String,
+    pub mint_fee: Uint128,
+    pub dev_fee: Uint128,
+}
+
+#[cw_serde]
+pub struct Cw20PricesResponse {
+    pub prices: Vec<Cw20PriceResponse>,
 }
@@ -1,11 +1,9 @@
-use std::f64::consts::E;
-
-use cosmwasm_std::{coin, coins, Addr, Coin, Empty, OwnedDeps};
-use cw_multi_test::{App, Contract, ContractWrapper, Executor, Router};
+use cosmwasm_std::{coins, Addr, Empty};
+use cw_multi_test::{App, Contract, ContractWrapper, Executor};
 use cw721::NumTokensResponse;
 use crate::{
     entry::{execute, instantiate, query},
-    msg::{ExecuteMsg, InstantiateMsg, QueryMsg, StatesResponse}
+    msg::{BalanceResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, StatesResponse}
 };
 fn cw721_base_latest_contract() -> Box<dyn Contract<Empty>> {
     let contract = ContractWrapper::new(
@@ -77,6 +75,20 @@ fn test_buy_check_balance() {
         reserved_amount: Some(0),
         dev_wallet: Some("john".to_string()),
         sale_time: Some(0),
+        history_depth: None,
+        burns_free_supply: None,
+        token_id_scheme: None,
+        uri_template: None,
+        uri_suffix: None,
+        max_reserved: None,
+        sale_end_time: None,
+        soft_cap: None,
+        purchase_mode: None,
+        max_per_block: None,
+        buy_cooldown: None,
+        operator_filter: None,
+        compliance: None,
+        voucher_signer: None,
     };
 
     let addr = app.instantiate_contract(code_id, owner.clone(), &instantiate_msg, &[], "Contract", None).unwrap();
@@ -127,4 +139,115 @@ fn test_buy_check_balance() {
             .u128(),
         400
     );
-}
\ No newline at end of file
+}
+/// Migrate a cw721-base 0.16 contract through every upgrade step.
+#[test]
+fn test_migrate_from_016() {
+    use cw721_base_016 as v16;
+    let mut app = App::default();
+    let admin = || Addr::unchecked("admin");
+
+    let code_id_016 = app.store_code(Box::new(ContractWrapper::new(
+        v16::entry::execute,
+        v16::entry::instantiate,
+        v16::entry::query,
+    )));
+    let code_id_latest = app.store_code(cw721_base_latest_contract());
+
+    let cw721 = app
+        .instantiate_contract(
+            code_id_016,
+            admin(),
+            &v16::InstantiateMsg {
+                name: "collection".to_string(),
+                symbol: "symbol".to_string(),
+                minter: admin().into_string(),
+            },
+            &[],
+            "cw721-base",
+            Some(admin().into_string()),
+        )
+        .unwrap();
+    app.execute_contract(
+        admin(),
+        cw721.clone(),
+        &v16::ExecuteMsg::<Empty, Empty>::Mint(v16::MintMsg {
+            token_id: "1".to_string(),
+            owner: "alice".to_string(),
+            token_uri: None,
+            extension: Empty {},
+        }),
+        &[],
+    )
+    .unwrap();
+
+    app.migrate_contract(
+        admin(),
+        cw721.clone(),
+        &MigrateMsg {
+            mint_fee: Some(5),
+            token_id_base: Some("Item".to_string()),
+            ..MigrateMsg::default()
+        },
+        code_id_latest,
+    )
+    .unwrap();
+
+    let ownership: cw_ownable::Ownership<Addr> = app
+        .wrap()
+        .query_wasm_smart(&cw721, &QueryMsg::<Empty>::Ownership {})
+        .unwrap();
+    assert_eq!(ownership.owner, Some(admin()));
+
+    let states: StatesResponse = app
+        .wrap()
+        .query_wasm_smart(&cw721, &QueryMsg::<Empty>::GetStates {})
+        .unwrap();
+    assert_eq!(states.mint_fee, 5);
+    assert_eq!(states.mint_per_tx, 1);
+    assert_eq!(states.total_supply, 1);
+
+    // the owner index and balances are rebuilt from the cw721-base tokens
+    let tokens: cw721::TokensResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &cw721,
+            &QueryMsg::<Empty>::Tokens {
+                owner: "alice".to_string(),
+                start_after: None,
+                start_before: None,
+                limit: None,
+                order: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(tokens.tokens, vec!["1".to_string()]);
+    let tokens: cw721::TokensResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &cw721,
+            &QueryMsg::<Empty>::TokensByIndex {
+                owner: "alice".to_string(),
+                start_after: None,
+                start_before: None,
+                limit: None,
+                order: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(tokens.tokens, vec!["1".to_string()]);
+    let balance: BalanceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            &cw721,
+            &QueryMsg::<Empty>::Balance {
+                owner: "alice".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(balance.balance, 1);
+
+    // migrating again to the same version is a no-op
+    app.migrate_contract(admin(), cw721, &MigrateMsg::default(), code_id_latest)
+        .unwrap();
+}
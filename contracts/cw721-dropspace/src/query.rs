@@ -2,19 +2,28 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use cosmwasm_std::{
-    to_json_binary, Addr, Binary, BlockInfo, CustomMsg, Deps, Env, Order, StdError, StdResult,
+    to_json_binary, Addr, Binary, BlockInfo, Coin, CustomMsg, Deps, Env, Order, StdError,
+    StdResult,
 };
 
 use cw721::{
     AllNftInfoResponse, ApprovalResponse, ApprovalsResponse, ContractInfoResponse, Cw721Query,
     Expiration, NftInfoResponse, NumTokensResponse, OperatorResponse, OperatorsResponse,
-    OwnerOfResponse, TokensResponse,
+    OrderBy, OwnerOfResponse, TokensResponse,
 };
 use cw_storage_plus::Bound;
 use cw_utils::maybe_addr;
 
-use crate::msg::{MinterResponse, QueryMsg, StatesResponse};
-use crate::state::{Approval, Cw721Contract, TokenInfo};
+use crate::msg::{
+    AllTokensInfoResponse, BalanceResponse, BlocklistResponse, Cw20PriceResponse,
+    Cw20PricesResponse, Holder, HoldersResponse, MinterResponse, NftInfoBatchResponse,
+    NumHoldersResponse, OperatorFilterResponse, OwnerOfBatchResponse, QueryMsg, RecipeResponse,
+    RecipesResponse, ReservedAmountResponse, SaleStatusResponse, SoftCapInfoResponse,
+    StatesResponse, SupplyInfoResponse, TokenAllNftInfo, TokenByIndexResponse,
+    TokenHistoryEntry, TokenHistoryResponse, TokenNftInfo, TokenOwnerOf,
+};
+use crate::state::{Approval, Cw721Contract, SaleStatus, TokenInfo};
+use crate::NATIVE_DENOM;
 
 const DEFAULT_LIMIT: u32 = 10;
 const MAX_LIMIT: u32 = 1000;
@@ -97,17 +106,22 @@ where
         owner: String,
         include_expired: bool,
         start_after: Option<String>,
+        start_before: Option<String>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
     ) -> StdResult<OperatorsResponse> {
         let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
         let start_addr = maybe_addr(deps.api, start_after)?;
         let start = start_addr.as_ref().map(Bound::exclusive);
+        let end_addr = maybe_addr(deps.api, start_before)?;
+        let end = end_addr.as_ref().map(Bound::exclusive);
+        let order = order.unwrap_or(OrderBy::Asc).into();
 
         let owner_addr = deps.api.addr_validate(&owner)?;
         let res: StdResult<Vec<_>> = self
             .operators
             .prefix(&owner_addr)
-            .range(deps.storage, start, None, Order::Ascending)
+            .range(deps.storage, start, end, order)
             .filter(|r| {
                 include_expired || r.is_err() || !r.as_ref().unwrap().1.is_expired(&env.block)
             })
@@ -183,10 +197,14 @@ where
         deps: Deps,
         owner: String,
         start_after: Option<String>,
+        start_before: Option<String>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
     ) -> StdResult<TokensResponse> {
         let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
         let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
+        let end = start_before.map(|s| Bound::ExclusiveRaw(s.into()));
+        let order = order.unwrap_or(OrderBy::Asc).into();
 
         let owner_addr = deps.api.addr_validate(&owner)?;
         let tokens: Vec<String> = self
@@ -194,7 +212,7 @@ where
             .idx
             .owner
             .prefix(owner_addr)
-            .keys(deps.storage, start, None, Order::Ascending)
+            .keys(deps.storage, start, end, order)
             .take(limit)
             .collect::<StdResult<Vec<_>>>()?;
 
@@ -205,14 +223,18 @@ where
         &self,
         deps: Deps,
         start_after: Option<String>,
+        start_before: Option<String>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
     ) -> StdResult<TokensResponse> {
         let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
         let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
+        let end = start_before.map(|s| Bound::ExclusiveRaw(s.into()));
+        let order = order.unwrap_or(OrderBy::Asc).into();
 
         let tokens: StdResult<Vec<String>> = self
             .tokens
-            .range(deps.storage, start, None, Order::Ascending)
+            .range(deps.storage, start, end, order)
             .take(limit)
             .map(|item| item.map(|(k, _)| k))
             .collect();
@@ -286,24 +308,81 @@ where
                 owner,
                 include_expired,
                 start_after,
+                start_before,
                 limit,
+                order,
             } => to_json_binary(&self.operators(
                 deps,
                 env,
                 owner,
                 include_expired.unwrap_or(false),
                 start_after,
+                start_before,
                 limit,
+                order,
             )?),
             QueryMsg::NumTokens {} => to_json_binary(&self.num_tokens(deps)?),
             QueryMsg::Tokens {
                 owner,
                 start_after,
+                start_before,
+                limit,
+                order,
+            } => to_json_binary(&self.tokens(
+                deps,
+                owner,
+                start_after,
+                start_before,
                 limit,
-            } => to_json_binary(&self.tokens(deps, owner, start_after, limit)?),
-            QueryMsg::AllTokens { start_after, limit } => {
-                to_json_binary(&self.all_tokens(deps, start_after, limit)?)
+                order,
+            )?),
+            QueryMsg::AllTokens {
+                start_after,
+                start_before,
+                limit,
+                order,
+            } => to_json_binary(&self.all_tokens(deps, start_after, start_before, limit, order)?),
+            QueryMsg::NftInfoBatch { token_ids } => {
+                to_json_binary(&self.nft_info_batch(deps, token_ids)?)
             }
+            QueryMsg::OwnerOfBatch {
+                token_ids,
+                include_expired,
+            } => to_json_binary(&self.owner_of_batch(
+                deps,
+                env,
+                token_ids,
+                include_expired.unwrap_or(false),
+            )?),
+            QueryMsg::AllTokensInfo { start_after, limit } => {
+                to_json_binary(&self.all_tokens_info(deps, env, start_after, limit)?)
+            }
+            QueryMsg::TokensByIndex {
+                owner,
+                start_after,
+                start_before,
+                limit,
+                order,
+            } => to_json_binary(&self.tokens_by_index(
+                deps,
+                owner,
+                start_after,
+                start_before,
+                limit,
+                order,
+            )?),
+            QueryMsg::AllTokensByIndex {
+                start_after,
+                start_before,
+                limit,
+                order,
+            } => to_json_binary(&self.all_tokens_by_index(
+                deps,
+                start_after,
+                start_before,
+                limit,
+                order,
+            )?),
             QueryMsg::Approval {
                 token_id,
                 spender,
@@ -327,7 +406,7 @@ where
             QueryMsg::Ownership {} => to_json_binary(&Self::ownership(deps)?),
             QueryMsg::Extension { msg: _ } => Ok(Binary::default()),
             QueryMsg::GetWithdrawAddress {} => {
-                to_json_binary(&self.withdraw_address.may_load(deps.storage)?)
+                to_json_binary(&self.config.load(deps.storage)?.withdraw_address)
             },
             QueryMsg::GetName {  } => {
                 let contract_info = self.contract_info.load(deps.storage)?;
@@ -338,77 +417,496 @@ where
                 to_json_binary(&contract_info.symbol)
             },
             QueryMsg::GetBaseUri {  } => {
-                let base_uri = self.base_uri.may_load(deps.storage)?.unwrap_or_else(|| "https://ipfs.io/ipfs/bafybeigrytqzipxv4sekrofqfz4etp4f6c7a3bssi5oyerccmeksm4czku/".into());
-                to_json_binary(&base_uri)
+                to_json_binary(&self.config.load(deps.storage)?.base_uri)
             }
             QueryMsg::GetMintPerTx {  } => {
-                to_json_binary(&self.mint_per_tx.may_load(deps.storage)?)
-            },
-            QueryMsg::GetMintPrice {  } => {
-                let mint_fee = self.mint_fee.may_load(deps.storage)?;
-                let dev_fee = self.dev_fee.may_load(deps.storage)?;
-                let mint_price = mint_fee.unwrap_or_else(|| 0) + dev_fee.unwrap_or_else(|| 0);
-                to_json_binary(&mint_price)
+                to_json_binary(&self.config.load(deps.storage)?.mint_per_tx)
             },
+            QueryMsg::GetMintPrice {  } => to_json_binary(&self.mint_prices(deps)?),
             QueryMsg::GetDevFee {  } => {
-                to_json_binary(&self.dev_fee.may_load(deps.storage)?)
+                to_json_binary(&self.config.load(deps.storage)?.dev_fee)
             },
             QueryMsg::GetMintFee {  } => {
-                to_json_binary(&self.mint_fee.may_load(deps.storage)?)
+                to_json_binary(&self.config.load(deps.storage)?.mint_fee)
             },
             QueryMsg::GetSupplyLimit {  } => {
-                to_json_binary(&self.supply_limit.may_load(deps.storage)?)
+                to_json_binary(&self.config.load(deps.storage)?.supply_limit)
             },
             QueryMsg::GetTotalSupply {  } => {
-                to_json_binary(&self.total_supply.may_load(deps.storage)?)
+                to_json_binary(&self.sale_state.load(deps.storage)?.total_supply)
             },
             QueryMsg::GetReservedAmount {  } => {
-                to_json_binary(&self.reserved_amount.may_load(deps.storage)?)
+                let config = self.config.load(deps.storage)?;
+                let sale_state = self.sale_state.load(deps.storage)?;
+                to_json_binary(&ReservedAmountResponse {
+                    reserved: sale_state.reserved_amount,
+                    max_reserved: config.max_reserved,
+                    remaining: sale_state.remaining_reserved(&config),
+                })
+            }
+            QueryMsg::GetReservedFor { address } => {
+                let address = deps.api.addr_validate(&address)?;
+                let reserved = self.reserved_by.may_load(deps.storage, &address)?;
+                to_json_binary(&reserved.unwrap_or_default())
             }
             QueryMsg::GetSaleTime {  } => {
-                to_json_binary(&self.sale_time.may_load(deps.storage)?)
+                to_json_binary(&self.config.load(deps.storage)?.sale_time)
             },
             QueryMsg::GetSaleStatus {  } => {
-                let sale_time = self.sale_time.may_load(deps.storage)?.unwrap_or_else(|| 0u64);
-                let sale_status = sale_time <= env.block.time.seconds();
-                to_json_binary(&sale_status)
+                let config = self.config.load(deps.storage)?;
+                let sale_state = self.sale_state.load(deps.storage)?;
+                to_json_binary(&SaleStatusResponse {
+                    status: config.sale_status(&sale_state, env.block.time.seconds()),
+                    sale_time: config.sale_time,
+                    sale_end_time: config.sale_end_time,
+                })
             },
+            QueryMsg::GetConfig {} => to_json_binary(&self.config.load(deps.storage)?),
+            QueryMsg::SupplyInfo {} => to_json_binary(&self.supply_info(deps)?),
+            QueryMsg::TokenByIndex { index } => {
+                to_json_binary(&self.token_by_index(deps, index)?)
+            }
             QueryMsg::GetStates {  } => {
-                let contract_info = self.contract_info.may_load(deps.storage)?.unwrap_or_else(|| ContractInfoResponse{
-                    name: "None".to_string(),
-                    symbol: "None".to_string()
-                });
-                let mint_per_tx = self.mint_per_tx.may_load(deps.storage)?.unwrap_or_else(|| 1u64);
-                let mint_fee = self.mint_fee.may_load(deps.storage)?.unwrap_or_else(|| 0u64);
-                let dev_fee = self.dev_fee.may_load(deps.storage)?.unwrap_or_else(|| 0u64);
-                let supply_limit = self.supply_limit.may_load(deps.storage)?.unwrap_or_else(|| 100000u64);
-                let total_supply = self.total_supply.may_load(deps.storage)?.unwrap_or_else(|| 0u64);
-                let reserved_amount = self.reserved_amount.may_load(deps.storage)?.unwrap_or_else(|| 0u64);
-                let withdraw_address = self.withdraw_address.may_load(deps.storage)?.unwrap_or_else(|| "None".to_string());
-                let dev_wallet = self.dev_wallet.may_load(deps.storage)?.unwrap_or_else(|| "None".to_string());
-                let sale_time = self.sale_time.may_load(deps.storage)?.unwrap_or_else(|| 0u64);
-                let base_uri = self.base_uri.may_load(deps.storage)?.unwrap_or_else(|| "None".to_string());
-                let sale_active = sale_time <= env.block.time.seconds();
+                let contract_info = self.contract_info.load(deps.storage)?;
+                let config = self.config.load(deps.storage)?;
+                let sale_state = self.sale_state.load(deps.storage)?;
+                let sale_active = config.sale_status(&sale_state, env.block.time.seconds())
+                    == SaleStatus::Active;
 
                 let state = StatesResponse{
                     name: contract_info.name,
                     symbol: contract_info.symbol,
-                    mint_price: mint_fee.clone() + dev_fee.clone(),
-                    mint_per_tx,
-                    mint_fee,
-                    dev_fee,
-                    supply_limit,
-                    total_supply,
-                    reserved_amount,
-                    withdraw_address,
-                    dev_wallet,
-                    sale_time,
-                    base_uri,
-                    sale_active
+                    mint_price: config.mint_fee + config.dev_fee,
+                    mint_per_tx: config.mint_per_tx,
+                    mint_fee: config.mint_fee,
+                    dev_fee: config.dev_fee,
+                    supply_limit: config.supply_limit,
+                    total_supply: sale_state.total_supply,
+                    reserved_amount: sale_state.reserved_amount,
+                    withdraw_address: config.withdraw_address.unwrap_or_else(|| "None".to_string()),
+                    dev_wallet: config.dev_wallet,
+                    sale_time: config.sale_time,
+                    sale_end_time: config.sale_end_time,
+                    base_uri: config.base_uri,
+                    sale_active,
+                    max_per_block: config.max_per_block,
+                    buy_cooldown: config.buy_cooldown,
                 };
                 to_json_binary(&state)
             }
+            QueryMsg::Escrow { address } => {
+                let address = deps.api.addr_validate(&address)?;
+                let escrow = self.escrows.may_load(deps.storage, &address)?;
+                to_json_binary(&escrow.unwrap_or_default())
+            }
+            QueryMsg::Blocklist { start_after, limit } => {
+                to_json_binary(&self.blocklist(deps, start_after, limit)?)
+            }
+            QueryMsg::OperatorFilter { start_after, limit } => {
+                to_json_binary(&self.operator_filter(deps, start_after, limit)?)
+            }
+            QueryMsg::OperatorAllowed { operator } => {
+                let operator = deps.api.addr_validate(&operator)?;
+                to_json_binary(&self.operator_allowed(deps.storage, &operator)?)
+            }
+            QueryMsg::VoucherUsed { nonce } => {
+                to_json_binary(&self.used_vouchers.has(deps.storage, nonce))
+            }
+            QueryMsg::PermitNonce { owner } => {
+                let owner = deps.api.addr_validate(&owner)?;
+                let nonce = self.permit_nonces.may_load(deps.storage, &owner)?;
+                to_json_binary(&nonce.unwrap_or_default())
+            }
+            QueryMsg::Commitment { address } => {
+                let address = deps.api.addr_validate(&address)?;
+                to_json_binary(&self.commitments.may_load(deps.storage, &address)?)
+            }
+            QueryMsg::SoftCapInfo {} => {
+                let config = self.config.load(deps.storage)?;
+                let sale_state = self.sale_state.load(deps.storage)?;
+                let sale_stats = self.sale_stats.may_load(deps.storage)?.unwrap_or_default();
+                to_json_binary(&SoftCapInfoResponse {
+                    soft_cap: config.soft_cap,
+                    sold: sale_stats.sold,
+                    funds_released: sale_state.funds_released,
+                    escrowed: self.escrowed.may_load(deps.storage)?.unwrap_or_default(),
+                })
+            }
+            QueryMsg::GetRedeemedSupply {} => {
+                to_json_binary(&self.redeemed_supply.may_load(deps.storage)?.unwrap_or_default())
+            }
+            QueryMsg::Recipe { recipe_id } => to_json_binary(&self.recipe(deps, recipe_id)?),
+            QueryMsg::Recipes { start_after, limit } => {
+                to_json_binary(&self.recipes(deps, start_after, limit)?)
+            }
+            QueryMsg::GetCw20Prices { start_after, limit } => {
+                to_json_binary(&self.cw20_prices(deps, start_after, limit)?)
+            }
+            QueryMsg::Balance { owner } => to_json_binary(&self.query_balance(deps, owner)?),
+            QueryMsg::Holders { start_after, limit } => {
+                to_json_binary(&self.holders(deps, start_after, limit)?)
+            }
+            QueryMsg::NumHolders {} => to_json_binary(&NumHoldersResponse {
+                count: self.num_holders(deps.storage)?,
+            }),
+            QueryMsg::TokenHistory {
+                token_id,
+                start_after,
+                limit,
+            } => to_json_binary(&self.token_history(deps, token_id, start_after, limit)?),
+            QueryMsg::SaleStats {} => {
+                to_json_binary(&self.sale_stats.may_load(deps.storage)?.unwrap_or_default())
+            }
+            QueryMsg::BuyerStats { address } => {
+                let buyer = deps.api.addr_validate(&address)?;
+                let stats = self.buyer_stats.may_load(deps.storage, &buyer)?;
+                to_json_binary(&stats.unwrap_or_default())
+            }
+        }
+    }
+
+    pub fn token_history(
+        &self,
+        deps: Deps,
+        token_id: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<TokenHistoryResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+
+        let history = self
+            .token_history
+            .prefix(&token_id)
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(seq, record)| TokenHistoryEntry { seq, record }))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(TokenHistoryResponse { history })
+    }
+
+    pub fn holders(
+        &self,
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<HoldersResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start_addr = maybe_addr(deps.api, start_after)?;
+        let start = start_addr.as_ref().map(Bound::exclusive);
+
+        let holders = self
+            .balances
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                item.map(|(address, count)| Holder {
+                    address: address.into_string(),
+                    count,
+                })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(HoldersResponse { holders })
+    }
+
+    pub fn blocklist(
+        &self,
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<BlocklistResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start_addr = maybe_addr(deps.api, start_after)?;
+        let start = start_addr.as_ref().map(Bound::exclusive);
+
+        let addresses = self
+            .blocklist
+            .keys(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(Addr::into_string))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(BlocklistResponse { addresses })
+    }
+
+    pub fn operator_filter(
+        &self,
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<OperatorFilterResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start_addr = maybe_addr(deps.api, start_after)?;
+        let start = start_addr.as_ref().map(Bound::exclusive);
+
+        let operators = self
+            .filtered_operators
+            .keys(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(Addr::into_string))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(OperatorFilterResponse {
+            filter: self.config.load(deps.storage)?.operator_filter,
+            operators,
+        })
+    }
+
+    pub fn query_balance(&self, deps: Deps, owner: String) -> StdResult<BalanceResponse> {
+        let owner_addr = deps.api.addr_validate(&owner)?;
+        let balance = self.balance(deps.storage, &owner_addr)?;
+        Ok(BalanceResponse { balance })
+    }
+
+    pub fn supply_info(&self, deps: Deps) -> StdResult<SupplyInfoResponse> {
+        let config = self.config.load(deps.storage)?;
+        let sale_state = self.sale_state.load(deps.storage)?;
+        Ok(SupplyInfoResponse {
+            minted: sale_state.minted,
+            burned: sale_state.burned,
+            circulating: sale_state.circulating(),
+            used_supply: sale_state.used_supply(&config),
+            supply_limit: config.supply_limit,
+            remaining: sale_state.remaining_supply(&config),
+            reserved: sale_state.reserved_amount,
+            remaining_reserved: sale_state.remaining_reserved(&config),
+            burns_free_supply: config.burns_free_supply,
+        })
+    }
+
+    /// Price of one NFT in every accepted native denom, `NATIVE_DENOM` first
+    pub fn mint_prices(&self, deps: Deps) -> StdResult<Vec<Coin>> {
+        let mut prices = vec![];
+        let config = self.config.load(deps.storage)?;
+        if let Some(price) = self.denom_price(deps.storage, &config, NATIVE_DENOM)? {
+            prices.push(Coin {
+                denom: NATIVE_DENOM.to_string(),
+                amount: price.total(),
+            });
+        }
+        for item in self
+            .denom_prices
+            .range(deps.storage, None, None, Order::Ascending)
+        {
+            let (denom, price) = item?;
+            prices.push(Coin {
+                denom,
+                amount: price.total(),
+            });
+        }
+        Ok(prices)
+    }
+
+    pub fn cw20_prices(
+        &self,
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<Cw20PricesResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start_addr = maybe_addr(deps.api, start_after)?;
+        let start = start_addr.as_ref().map(Bound::exclusive);
+
+        let prices = self
+            .cw20_prices
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                item.map(|(token, price)| Cw20PriceResponse {
+                    token: token.into_string(),
+                    mint_fee: price.mint_fee,
+                    dev_fee: price.dev_fee,
+                })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(Cw20PricesResponse { prices })
+    }
+
+    pub fn recipe(&self, deps: Deps, recipe_id: u64) -> StdResult<RecipeResponse<T>> {
+        let recipe = self.recipes.load(deps.storage, recipe_id)?;
+        Ok(RecipeResponse { recipe_id, recipe })
+    }
+
+    pub fn recipes(
+        &self,
+        deps: Deps,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<RecipesResponse<T>> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+
+        let recipes = self
+            .recipes
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(recipe_id, recipe)| RecipeResponse { recipe_id, recipe }))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(RecipesResponse { recipes })
+    }
+
+    pub fn nft_info_batch(
+        &self,
+        deps: Deps,
+        token_ids: Vec<String>,
+    ) -> StdResult<NftInfoBatchResponse<T>> {
+        assert_batch_size(&token_ids)?;
+        let tokens = token_ids
+            .into_iter()
+            .map(|token_id| {
+                let info = self.nft_info(deps, token_id.clone())?;
+                Ok(TokenNftInfo { token_id, info })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(NftInfoBatchResponse { tokens })
+    }
+
+    pub fn owner_of_batch(
+        &self,
+        deps: Deps,
+        env: Env,
+        token_ids: Vec<String>,
+        include_expired: bool,
+    ) -> StdResult<OwnerOfBatchResponse> {
+        assert_batch_size(&token_ids)?;
+        let tokens = token_ids
+            .into_iter()
+            .map(|token_id| {
+                let access = self.owner_of(deps, env.clone(), token_id.clone(), include_expired)?;
+                Ok(TokenOwnerOf { token_id, access })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(OwnerOfBatchResponse { tokens })
+    }
+
+    pub fn all_tokens_info(
+        &self,
+        deps: Deps,
+        env: Env,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<AllTokensInfoResponse<T>> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start = start_after.map(|s| Bound::ExclusiveRaw(s.into()));
+
+        let tokens = self
+            .tokens
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                let (token_id, info) = item?;
+                let info = AllNftInfoResponse {
+                    access: OwnerOfResponse {
+                        owner: info.owner.to_string(),
+                        approvals: humanize_approvals(&env.block, &info, false),
+                    },
+                    info: NftInfoResponse {
+                        token_uri: info.token_uri,
+                        extension: info.extension,
+                    },
+                };
+                Ok(TokenAllNftInfo { token_id, info })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(AllTokensInfoResponse { tokens })
+    }
+
+    pub fn tokens_by_index(
+        &self,
+        deps: Deps,
+        owner: String,
+        start_after: Option<String>,
+        start_before: Option<String>,
+        limit: Option<u32>,
+        order: Option<OrderBy>,
+    ) -> StdResult<TokensResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let min = self.index_bound(deps, start_after)?;
+        let max = self.index_bound(deps, start_before)?;
+        let order = order.unwrap_or(OrderBy::Asc).into();
+
+        let owner_addr = deps.api.addr_validate(&owner)?;
+        let tokens = self
+            .tokens
+            .idx
+            .owner_index
+            .sub_prefix(owner_addr)
+            .keys(deps.storage, min, max, order)
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(TokensResponse { tokens })
+    }
+
+    pub fn all_tokens_by_index(
+        &self,
+        deps: Deps,
+        start_after: Option<String>,
+        start_before: Option<String>,
+        limit: Option<u32>,
+        order: Option<OrderBy>,
+    ) -> StdResult<TokensResponse> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let min = self.index_bound(deps, start_after)?;
+        let max = self.index_bound(deps, start_before)?;
+        let order = order.unwrap_or(OrderBy::Asc).into();
+
+        let tokens = self
+            .tokens
+            .idx
+            .index
+            .keys(deps.storage, min, max, order)
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(TokensResponse { tokens })
+    }
+
+    /// Position of `token_id` in the mint index ordering. A burned token is placed by
+    /// the index its id was minted with, or among the redeemed NFTs.
+    fn index_bound(
+        &self,
+        deps: Deps,
+        token_id: Option<String>,
+    ) -> StdResult<Option<Bound<'a, (u64, String)>>> {
+        token_id
+            .map(|token_id| {
+                let index = match self.tokens.may_load(deps.storage, &token_id)? {
+                    Some(token) => token.index,
+                    None => {
+                        let config = self.config.load(deps.storage)?;
+                        config
+                            .token_id_scheme
+                            .index(&config.token_id_base, &token_id)
+                    }
+                };
+                Ok(Bound::exclusive((index.unwrap_or(u64::MAX), token_id)))
+            })
+            .transpose()
+    }
+
+    /// Token id of the NFT minted at `index`, error if it was burned
+    pub fn token_by_index(&self, deps: Deps, index: u64) -> StdResult<TokenByIndexResponse> {
+        for item in self
+            .tokens
+            .idx
+            .index
+            .prefix(index)
+            .range(deps.storage, None, None, Order::Ascending)
+        {
+            let (token_id, token) = item?;
+            if token.index == Some(index) {
+                return Ok(TokenByIndexResponse { token_id });
+            }
         }
+        Err(StdError::not_found(format!("token with index {}", index)))
     }
 
     pub fn minter(&self, deps: Deps) -> StdResult<MinterResponse> {
@@ -431,6 +929,16 @@ fn parse_approval(item: StdResult<(Addr, Expiration)>) -> StdResult<cw721::Appro
     })
 }
 
+/// Batched queries take as many token ids as paginated ones return tokens
+fn assert_batch_size(token_ids: &[String]) -> StdResult<()> {
+    if token_ids.len() > MAX_LIMIT as usize {
+        return Err(StdError::generic_err(format!(
+            "At most {MAX_LIMIT} token ids can be queried at once"
+        )));
+    }
+    Ok(())
+}
+
 fn humanize_approvals<T>(
     block: &BlockInfo,
     info: &TokenInfo<T>,
@@ -3,10 +3,19 @@ use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 
-use cosmwasm_std::{Addr, BlockInfo, CustomMsg, StdResult, Storage};
+use bech32::{ToBase32, Variant};
+use cosmwasm_std::{
+    to_json_vec, Addr, Binary, BlockInfo, Coin, CustomMsg, Empty, Order, StdError, StdResult,
+    Storage, Timestamp, Uint128,
+};
 
+use cw20::Denom;
 use cw721::{ContractInfoResponse, Cw721, Expiration};
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+use crate::NATIVE_DENOM;
 
 pub struct Cw721Contract<'a, T, C, E, Q>
 where
@@ -16,22 +25,64 @@ where
 {
     pub contract_info: Item<'a, ContractInfoResponse>,
     pub token_count: Item<'a, u64>,
-    pub base_uri: Item<'a, String>,
-    pub token_id_base: Item<'a, String>,
+    /// Sale settings, updated by the owner
+    pub config: Item<'a, Config>,
+    /// Sale counters, updated by mints
+    pub sale_state: Item<'a, SaleState>,
+    /// Number of NFTs reserved for each recipient
+    pub reserved_by: Map<'a, &'a Addr, u64>,
 
     /// Stored as (granter, operator) giving operator full control over granter's account
     pub operators: Map<'a, (&'a Addr, &'a Addr), Expiration>,
     pub tokens: IndexedMap<'a, &'a str, TokenInfo<T>, TokenIndexes<'a, T>>,
-    pub withdraw_address: Item<'a, String>,
 
-    pub reserved_amount: Item<'a, u64>,
-    pub mint_per_tx: Item<'a, u64>,
-    pub mint_fee: Item<'a, u64>,
-    pub dev_fee: Item<'a, u64>,
-    pub supply_limit: Item<'a, u64>,
-    pub total_supply: Item<'a, u64>,
-    pub dev_wallet: Item<'a, String>,
-    pub sale_time: Item<'a, u64>,
+    /// Burn-to-mint redemption recipes, keyed by recipe id
+    pub recipes: Map<'a, u64, Recipe<T>>,
+    pub recipe_count: Item<'a, u64>,
+    /// Number of tokens minted through redemptions
+    pub redeemed_supply: Item<'a, u64>,
+
+    /// Accepted CW20 tokens and their price per NFT
+    pub cw20_prices: Map<'a, &'a Addr, Price>,
+    /// Accepted native denoms besides `NATIVE_DENOM` and their price per NFT
+    pub denom_prices: Map<'a, &'a str, Price>,
+
+    /// Number of NFTs held by each owner, only holders of at least one NFT are stored
+    pub balances: Map<'a, &'a Addr, u64>,
+    pub num_holders: Item<'a, u64>,
+
+    /// Append-only ownership history of every token, keyed by (token_id, sequence)
+    pub token_history: Map<'a, (&'a str, u64), HistoryRecord>,
+    /// Sequence number of the next history record of every token
+    pub history_seq: Map<'a, &'a str, u64>,
+    /// Maximum number of history records kept per token, unlimited if unset
+    pub history_depth: Item<'a, u64>,
+
+    pub sale_stats: Item<'a, SaleStats>,
+    pub buyer_stats: Map<'a, &'a Addr, BuyerStats>,
+
+    /// Payments and NFTs of every buyer while the sale proceeds are held in escrow
+    pub escrows: Map<'a, &'a Addr, Escrow>,
+    /// Sale proceeds held in escrow until the soft cap is reached
+    pub escrowed: Item<'a, Vec<EscrowedPayment>>,
+
+    /// Time of the last purchase of every buyer, only tracked with a `buy_cooldown`
+    pub last_buy: Map<'a, &'a Addr, u64>,
+    /// Addresses that can't buy or receive NFTs
+    pub blocklist: Map<'a, &'a Addr, Empty>,
+    /// Operators allowed or denied by `Config::operator_filter`
+    pub filtered_operators: Map<'a, &'a Addr, Empty>,
+    /// Pending `CommitBuy` purchases, one per buyer
+    pub commitments: Map<'a, &'a Addr, Commitment>,
+    /// Payments of the pending commitments, totalled by denom
+    pub committed: Item<'a, Vec<Coin>>,
+    /// Mint index at every pool position moved by a random draw, any other position
+    /// `p` holds index `p + 1`
+    pub pool_swaps: Map<'a, u64, u64>,
+    /// Nonce the next `Permit` of each owner must be signed with
+    pub permit_nonces: Map<'a, &'a Addr, u64>,
+    /// Nonces of the vouchers already bought with
+    pub used_vouchers: Map<'a, u64, Empty>,
 
     pub(crate) _custom_response: PhantomData<C>,
     pub(crate) _custom_query: PhantomData<Q>,
@@ -58,20 +109,36 @@ where
         Self::new(
             "nft_info",
             "num_tokens",
-            "base_uri",
-            "token_id_base",
+            "config",
+            "sale_state",
+            "reserved_by",
             "operators",
             "tokens",
             "tokens_owner",
-            "withdraw_address",
-            "reserved_amount",
-            "mint_per_tx",
-            "mint_fee",
-            "dev_fee",
-            "supply_limit",
-            "total supply",
-            "dev_wallet",
-            "sale_time",
+            "tokens_index",
+            "tokens_owner_index",
+            "recipes",
+            "recipe_count",
+            "redeemed_supply",
+            "cw20_prices",
+            "denom_prices",
+            "balances",
+            "num_holders",
+            "token_history",
+            "history_seq",
+            "history_depth",
+            "sale_stats",
+            "buyer_stats",
+            "escrows",
+            "escrowed",
+            "last_buy",
+            "blocklist",
+            "filtered_operators",
+            "commitments",
+            "committed",
+            "pool_swaps",
+            "permit_nonces",
+            "used_vouchers",
         )
     }
 }
@@ -82,45 +149,80 @@ where
     E: CustomMsg,
     Q: CustomMsg,
 {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         contract_key: &'a str,
         token_count_key: &'a str,
-        base_uri: &'a str,
-        token_id_base: &'a str,
+        config_key: &'a str,
+        sale_state_key: &'a str,
+        reserved_by_key: &'a str,
         operator_key: &'a str,
         tokens_key: &'a str,
         tokens_owner_key: &'a str,
-        withdraw_address_key: &'a str,
-
-        reserved_amount: &'a str,
-        mint_per_tx: &'a str,
-        mint_fee: &'a str,
-        dev_fee: &'a str,
-        supply_limit: &'a str,
-        total_supply: &'a str,
-        dev_wallet: &'a str,
-        sale_time: &'a str,
+        tokens_index_key: &'a str,
+        tokens_owner_index_key: &'a str,
+        recipes_key: &'a str,
+        recipe_count_key: &'a str,
+        redeemed_supply_key: &'a str,
+        cw20_prices_key: &'a str,
+        denom_prices_key: &'a str,
+        balances_key: &'a str,
+        num_holders_key: &'a str,
+        token_history_key: &'a str,
+        history_seq_key: &'a str,
+        history_depth_key: &'a str,
+        sale_stats_key: &'a str,
+        buyer_stats_key: &'a str,
+        escrows_key: &'a str,
+        escrowed_key: &'a str,
+        last_buy_key: &'a str,
+        blocklist_key: &'a str,
+        filtered_operators_key: &'a str,
+        commitments_key: &'a str,
+        committed_key: &'a str,
+        pool_swaps_key: &'a str,
+        permit_nonces_key: &'a str,
+        used_vouchers_key: &'a str,
     ) -> Self {
         let indexes = TokenIndexes {
             owner: MultiIndex::new(token_owner_idx, tokens_key, tokens_owner_key),
+            index: MultiIndex::new(token_index_idx, tokens_key, tokens_index_key),
+            owner_index: MultiIndex::new(
+                token_owner_index_idx,
+                tokens_key,
+                tokens_owner_index_key,
+            ),
         };
         Self {
             contract_info: Item::new(contract_key),
             token_count: Item::new(token_count_key),
-            base_uri: Item::new(base_uri),
-            token_id_base: Item::new(token_id_base),
+            config: Item::new(config_key),
+            sale_state: Item::new(sale_state_key),
+            reserved_by: Map::new(reserved_by_key),
             operators: Map::new(operator_key),
             tokens: IndexedMap::new(tokens_key, indexes),
-            withdraw_address: Item::new(withdraw_address_key),
-
-            reserved_amount: Item::new(reserved_amount),
-            mint_per_tx: Item::new(mint_per_tx),
-            mint_fee: Item::new(mint_fee),
-            dev_fee: Item::new(dev_fee),
-            supply_limit: Item::new(supply_limit),
-            total_supply: Item::new(total_supply),
-            dev_wallet: Item::new(dev_wallet),
-            sale_time: Item::new(sale_time),
+            recipes: Map::new(recipes_key),
+            recipe_count: Item::new(recipe_count_key),
+            redeemed_supply: Item::new(redeemed_supply_key),
+            cw20_prices: Map::new(cw20_prices_key),
+            denom_prices: Map::new(denom_prices_key),
+            balances: Map::new(balances_key),
+            num_holders: Item::new(num_holders_key),
+            token_history: Map::new(token_history_key),
+            history_seq: Map::new(history_seq_key),
+            history_depth: Item::new(history_depth_key),
+            sale_stats: Item::new(sale_stats_key),
+            buyer_stats: Map::new(buyer_stats_key),
+            escrows: Map::new(escrows_key),
+            escrowed: Item::new(escrowed_key),
+            last_buy: Map::new(last_buy_key),
+            blocklist: Map::new(blocklist_key),
+            filtered_operators: Map::new(filtered_operators_key),
+            commitments: Map::new(commitments_key),
+            committed: Item::new(committed_key),
+            pool_swaps: Map::new(pool_swaps_key),
+            permit_nonces: Map::new(permit_nonces_key),
+            used_vouchers: Map::new(used_vouchers_key),
 
             _custom_response: PhantomData,
             _custom_execute: PhantomData,
@@ -143,6 +245,263 @@ where
         self.token_count.save(storage, &val)?;
         Ok(val)
     }
+
+    pub fn balance(&self, storage: &dyn Storage, owner: &Addr) -> StdResult<u64> {
+        Ok(self.balances.may_load(storage, owner)?.unwrap_or_default())
+    }
+
+    pub fn increment_balance(&self, storage: &mut dyn Storage, owner: &Addr) -> StdResult<u64> {
+        let val = self.balance(storage, owner)? + 1;
+        self.balances.save(storage, owner, &val)?;
+        if val == 1 {
+            let holders = self.num_holders(storage)? + 1;
+            self.num_holders.save(storage, &holders)?;
+        }
+        Ok(val)
+    }
+
+    pub fn decrement_balance(&self, storage: &mut dyn Storage, owner: &Addr) -> StdResult<u64> {
+        let val = self.balance(storage, owner)? - 1;
+        if val == 0 {
+            self.balances.remove(storage, owner);
+            let holders = self.num_holders(storage)? - 1;
+            self.num_holders.save(storage, &holders)?;
+        } else {
+            self.balances.save(storage, owner, &val)?;
+        }
+        Ok(val)
+    }
+
+    pub fn num_holders(&self, storage: &dyn Storage) -> StdResult<u64> {
+        Ok(self.num_holders.may_load(storage)?.unwrap_or_default())
+    }
+
+    /// Appends a record to the history of `token_id`, dropping the oldest records
+    /// beyond `history_depth`
+    pub fn push_history(
+        &self,
+        storage: &mut dyn Storage,
+        token_id: &str,
+        record: &HistoryRecord,
+    ) -> StdResult<u64> {
+        let seq = self.history_seq.may_load(storage, token_id)?.unwrap_or_default();
+        self.token_history.save(storage, (token_id, seq), record)?;
+        self.history_seq.save(storage, token_id, &(seq + 1))?;
+
+        if let Some(depth) = self.history_depth.may_load(storage)? {
+            let keep_from = (seq + 1).saturating_sub(depth);
+            let stale = self
+                .token_history
+                .prefix(token_id)
+                .keys(storage, None, Some(Bound::exclusive(keep_from)), Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()?;
+            for stale_seq in stale {
+                self.token_history.remove(storage, (token_id, stale_seq));
+            }
+        }
+        Ok(seq)
+    }
+
+    /// Records `qty` NFTs bought by `buyer` for the given fees, paid in `denom`
+    pub fn record_purchase(
+        &self,
+        storage: &mut dyn Storage,
+        buyer: &Addr,
+        qty: u64,
+        denom: &Denom,
+        mint_fee: Uint128,
+        dev_fee: Uint128,
+    ) -> StdResult<()> {
+        if qty == 0 {
+            return Ok(());
+        }
+
+        let mut stats = self.sale_stats.may_load(storage)?.unwrap_or_default();
+        let mut buyer_stats = self.buyer_stats.may_load(storage, buyer)?.unwrap_or_default();
+        if buyer_stats.bought == 0 {
+            stats.buyers += 1;
+        }
+        stats.sold += qty;
+        buyer_stats.bought += qty;
+        match denom {
+            Denom::Native(denom) => {
+                add_amount(&mut stats.revenue, denom, mint_fee + dev_fee);
+                add_amount(&mut stats.mint_fees, denom, mint_fee);
+                add_amount(&mut stats.dev_fees, denom, dev_fee);
+                add_amount(&mut buyer_stats.spent, denom, mint_fee + dev_fee);
+            }
+            Denom::Cw20(token) => {
+                add_amount(&mut stats.cw20_revenue, token.as_str(), mint_fee + dev_fee);
+                add_amount(&mut stats.cw20_mint_fees, token.as_str(), mint_fee);
+                add_amount(&mut stats.cw20_dev_fees, token.as_str(), dev_fee);
+                add_amount(&mut buyer_stats.cw20_spent, token.as_str(), mint_fee + dev_fee);
+            }
+        }
+        self.sale_stats.save(storage, &stats)?;
+        self.buyer_stats.save(storage, buyer, &buyer_stats)
+    }
+
+    /// Holds the fees paid by `buyer` for `token_ids` in escrow
+    pub fn record_escrow(
+        &self,
+        storage: &mut dyn Storage,
+        buyer: &Addr,
+        token_ids: Vec<String>,
+        denom: &Denom,
+        mint_fee: Uint128,
+        dev_fee: Uint128,
+    ) -> StdResult<()> {
+        let mut escrow = self.escrows.may_load(storage, buyer)?.unwrap_or_default();
+        escrow.token_ids.extend(token_ids);
+        add_payment(&mut escrow.payments, denom, mint_fee, dev_fee);
+        self.escrows.save(storage, buyer, &escrow)?;
+
+        let mut escrowed = self.escrowed.may_load(storage)?.unwrap_or_default();
+        add_payment(&mut escrowed, denom, mint_fee, dev_fee);
+        self.escrowed.save(storage, &escrowed)
+    }
+
+    /// Takes a refunded escrow out of the sale and buyer stats
+    pub fn record_refund(&self, storage: &mut dyn Storage, buyer: &Addr, escrow: &Escrow) -> StdResult<()> {
+        let qty = escrow.token_ids.len() as u64;
+        let mut stats = self.sale_stats.may_load(storage)?.unwrap_or_default();
+        let mut buyer_stats = self.buyer_stats.may_load(storage, buyer)?.unwrap_or_default();
+        stats.sold = stats.sold.saturating_sub(qty);
+        buyer_stats.bought = buyer_stats.bought.saturating_sub(qty);
+        if buyer_stats.bought == 0 {
+            stats.buyers = stats.buyers.saturating_sub(1);
+        }
+        for payment in &escrow.payments {
+            let (mint_fee, dev_fee) = (payment.mint_fee, payment.dev_fee);
+            match &payment.denom {
+                Denom::Native(denom) => {
+                    sub_amount(&mut stats.revenue, denom, mint_fee + dev_fee);
+                    sub_amount(&mut stats.mint_fees, denom, mint_fee);
+                    sub_amount(&mut stats.dev_fees, denom, dev_fee);
+                    sub_amount(&mut buyer_stats.spent, denom, mint_fee + dev_fee);
+                }
+                Denom::Cw20(token) => {
+                    sub_amount(&mut stats.cw20_revenue, token.as_str(), mint_fee + dev_fee);
+                    sub_amount(&mut stats.cw20_mint_fees, token.as_str(), mint_fee);
+                    sub_amount(&mut stats.cw20_dev_fees, token.as_str(), dev_fee);
+                    sub_amount(&mut buyer_stats.cw20_spent, token.as_str(), mint_fee + dev_fee);
+                }
+            }
+        }
+        self.sale_stats.save(storage, &stats)?;
+        if buyer_stats == BuyerStats::default() {
+            self.buyer_stats.remove(storage, buyer);
+            Ok(())
+        } else {
+            self.buyer_stats.save(storage, buyer, &buyer_stats)
+        }
+    }
+
+    /// Amount of native `denom` held for buyers, in escrow or as pending commitments
+    pub fn locked_funds(&self, storage: &dyn Storage, denom: &str) -> StdResult<Uint128> {
+        let native = Denom::Native(denom.to_string());
+        let mut locked: Uint128 = self
+            .escrowed
+            .may_load(storage)?
+            .unwrap_or_default()
+            .iter()
+            .filter(|payment| payment.denom == native)
+            .map(EscrowedPayment::total)
+            .sum();
+        if let Some(committed) = self
+            .committed
+            .may_load(storage)?
+            .unwrap_or_default()
+            .into_iter()
+            .find(|coin| coin.denom == denom)
+        {
+            locked += committed.amount;
+        }
+        Ok(locked)
+    }
+
+    /// Saves the commitment of `buyer` and adds its payment to the committed total
+    pub fn save_commitment(
+        &self,
+        storage: &mut dyn Storage,
+        buyer: &Addr,
+        commitment: &Commitment,
+    ) -> StdResult<()> {
+        self.commitments.save(storage, buyer, commitment)?;
+        let mut committed = self.committed.may_load(storage)?.unwrap_or_default();
+        add_amount(&mut committed, &commitment.payment.denom, commitment.payment.amount);
+        self.committed.save(storage, &committed)
+    }
+
+    /// Removes the commitment of `buyer` and takes its payment out of the committed total
+    pub fn remove_commitment(
+        &self,
+        storage: &mut dyn Storage,
+        buyer: &Addr,
+        commitment: &Commitment,
+    ) -> StdResult<()> {
+        self.commitments.remove(storage, buyer);
+        let mut committed = self.committed.may_load(storage)?.unwrap_or_default();
+        sub_amount(&mut committed, &commitment.payment.denom, commitment.payment.amount);
+        self.committed.save(storage, &committed)
+    }
+
+    /// Takes the mint index at pool position `pick` and moves the index at `next`, the
+    /// first unused position, in its place
+    pub fn take_pool_index(&self, storage: &mut dyn Storage, next: u64, pick: u64) -> StdResult<u64> {
+        let index = self.pool_index(storage, pick)?;
+        if pick != next {
+            let moved = self.pool_index(storage, next)?;
+            self.pool_swaps.save(storage, pick, &moved)?;
+        }
+        self.pool_swaps.remove(storage, next);
+        Ok(index)
+    }
+
+    fn pool_index(&self, storage: &dyn Storage, position: u64) -> StdResult<u64> {
+        Ok(self
+            .pool_swaps
+            .may_load(storage, position)?
+            .unwrap_or(position + 1))
+    }
+
+    /// Whether `operator` can be approved and transfer NFTs for their owners
+    pub fn operator_allowed(&self, storage: &dyn Storage, operator: &Addr) -> StdResult<bool> {
+        let filter = self.config.load(storage)?.operator_filter;
+        Ok(match filter {
+            OperatorFilter::Off => true,
+            OperatorFilter::Allow => self.filtered_operators.has(storage, operator),
+            OperatorFilter::Deny => !self.filtered_operators.has(storage, operator),
+        })
+    }
+
+    pub fn record_reserve(&self, storage: &mut dyn Storage, qty: u64) -> StdResult<()> {
+        let mut stats = self.sale_stats.may_load(storage)?.unwrap_or_default();
+        stats.reserved += qty;
+        self.sale_stats.save(storage, &stats)
+    }
+
+    /// Returns the price of one NFT in `denom`, or `None` if the denom is not accepted
+    pub fn denom_price(
+        &self,
+        storage: &dyn Storage,
+        config: &Config,
+        denom: &str,
+    ) -> StdResult<Option<Price>> {
+        if denom == NATIVE_DENOM {
+            return Ok(Some(Price {
+                mint_fee: config.mint_fee.into(),
+                dev_fee: config.dev_fee.into(),
+            }));
+        }
+        self.denom_prices.may_load(storage, denom)
+    }
+
+    pub fn increment_recipes(&self, storage: &mut dyn Storage) -> StdResult<u64> {
+        let val = self.recipe_count.may_load(storage)?.unwrap_or_default() + 1;
+        self.recipe_count.save(storage, &val)?;
+        Ok(val)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -159,6 +518,10 @@ pub struct TokenInfo<T> {
 
     /// You can add any custom metadata here when you extend cw721-dropspace
     pub extension: T,
+
+    /// Position in the mint sequence, unset for redeemed NFTs
+    #[serde(default)]
+    pub index: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -175,11 +538,472 @@ impl Approval {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HistoryRecord {
+    pub action: HistoryAction,
+    /// Account that executed the action, the minter for mints
+    pub sender: Addr,
+    /// Owner before the action, unset for mints
+    pub from: Option<Addr>,
+    /// Owner after the action, unset for burns
+    pub to: Option<Addr>,
+    pub block_height: u64,
+    pub timestamp: Timestamp,
+}
+
+impl HistoryRecord {
+    pub fn new(
+        action: HistoryAction,
+        sender: &Addr,
+        from: Option<&Addr>,
+        to: Option<&Addr>,
+        block: &BlockInfo,
+    ) -> Self {
+        HistoryRecord {
+            action,
+            sender: sender.clone(),
+            from: from.cloned(),
+            to: to.cloned(),
+            block_height: block.height,
+            timestamp: block.time,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryAction {
+    Mint,
+    Transfer,
+    Send,
+    Burn,
+}
+
+/// Which addresses can be approved and transfer NFTs for their owners
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OperatorFilter {
+    /// Any address
+    #[default]
+    Off,
+    /// Only the filtered operators
+    Allow,
+    /// Any address but the filtered operators
+    Deny,
+}
+
+/// How NFTs are bought
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PurchaseMode {
+    /// `Buy` mints the next NFTs right away
+    #[default]
+    Direct,
+    /// `CommitBuy` pays for a hidden purchase, revealed with `RevealBuy` from
+    /// `reveal_delay` blocks later to mint random NFTs. Unrevealed purchases are
+    /// refunded from `reveal_timeout` blocks after the commitment.
+    CommitReveal {
+        reveal_delay: u64,
+        reveal_timeout: u64,
+    },
+}
+
+/// A paid purchase waiting to be revealed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Commitment {
+    /// See `Commitment::hash`
+    pub hash: Binary,
+    pub payment: Coin,
+    /// First block height the purchase can be revealed at
+    pub reveal_from: u64,
+    /// Block height from which the purchase can only be refunded
+    pub expires: u64,
+    /// Mixed with the salt and the reveal block to draw the revealed NFTs
+    pub entropy: Binary,
+}
+
+impl Commitment {
+    /// SHA-256 of the buyer address, `qty` as 8 big-endian bytes and `salt`
+    pub fn hash(buyer: &Addr, qty: u64, salt: &[u8]) -> Binary {
+        let mut hasher = Sha256::new();
+        hasher.update(buyer.as_bytes());
+        hasher.update(qty.to_be_bytes());
+        hasher.update(salt);
+        Binary::from(hasher.finalize().as_slice())
+    }
+}
+
+/// What an owner signs to grant a `Permit`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PermitPayload {
+    pub chain_id: String,
+    pub contract: String,
+    pub spender: String,
+    /// `None` approves `spender` as operator of all the owner's tokens
+    pub token_id: Option<String>,
+    pub expires: Option<Expiration>,
+    pub nonce: u64,
+}
+
+impl PermitPayload {
+    /// SHA-256 of the JSON encoded payload, the message hash the signature is checked against
+    pub fn hash(&self) -> StdResult<[u8; 32]> {
+        Ok(Sha256::digest(to_json_vec(self)?).into())
+    }
+}
+
+/// What the voucher signer signs to let `buyer` buy up to `max_qty` NFTs at `price` each
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VoucherPayload {
+    pub chain_id: String,
+    pub contract: String,
+    pub buyer: String,
+    pub max_qty: u64,
+    pub price: Coin,
+    /// Seconds since epoch the voucher can no longer be used from
+    pub expires: u64,
+    pub nonce: u64,
+}
+
+impl VoucherPayload {
+    /// SHA-256 of the JSON encoded payload, the message hash the signature is checked against
+    pub fn hash(&self) -> StdResult<[u8; 32]> {
+        Ok(Sha256::digest(to_json_vec(self)?).into())
+    }
+}
+
+/// Address of a compressed secp256k1 public key with the given bech32 `prefix`
+pub fn pubkey_address(prefix: &str, pubkey: &[u8]) -> StdResult<String> {
+    let hash = Ripemd160::digest(Sha256::digest(pubkey));
+    bech32::encode(prefix, hash.to_base32(), Variant::Bech32)
+        .map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+/// How token ids are built from `token_id_base` and the mint index
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenIdScheme {
+    /// `"{token_id_base} #{index}"`
+    #[default]
+    Hash,
+    /// `"{index}"`
+    Number,
+    /// `"{token_id_base}{index}"`
+    Prefix,
+    /// `"{token_id_base}{index}"` with the index padded with zeros to `width` digits
+    ZeroPadded { width: u32 },
+}
+
+impl TokenIdScheme {
+    pub fn token_id(&self, base: &str, index: u64) -> String {
+        match self {
+            TokenIdScheme::Hash => format!("{} #{}", base, index),
+            TokenIdScheme::Number => index.to_string(),
+            TokenIdScheme::Prefix => format!("{}{}", base, index),
+            TokenIdScheme::ZeroPadded { width } => {
+                format!("{}{:0width$}", base, index, width = *width as usize)
+            }
+        }
+    }
+
+    /// Mint index `token_id` was given by this scheme, `None` for redeemed and other ids
+    pub fn index(&self, base: &str, token_id: &str) -> Option<u64> {
+        let digits = match self {
+            TokenIdScheme::Number => Some(token_id),
+            TokenIdScheme::Hash => token_id.strip_prefix(base)?.strip_prefix(" #"),
+            TokenIdScheme::Prefix | TokenIdScheme::ZeroPadded { .. } => token_id.strip_prefix(base),
+        }?;
+        let index = digits.parse().ok()?;
+        (self.token_id(base, index) == token_id).then_some(index)
+    }
+
+    /// Id of the `n`th redeemed NFT, redemptions get their own sequence so they never
+    /// collide with minted ones
+    pub fn redemption_id(&self, base: &str, n: u64) -> String {
+        match self {
+            TokenIdScheme::Hash => format!("{} #R{}", base, n),
+            TokenIdScheme::Number => format!("R{}", n),
+            TokenIdScheme::Prefix => format!("{}R{}", base, n),
+            TokenIdScheme::ZeroPadded { width } => {
+                format!("{}R{:0width$}", base, n, width = *width as usize)
+            }
+        }
+    }
+}
+
+/// Widest `TokenIdScheme::ZeroPadded` width, the number of digits of `u64::MAX`
+pub const MAX_ID_WIDTH: u32 = 20;
+
+/// Default `Config::uri_template`
+pub const DEFAULT_URI_TEMPLATE: &str = "{base_uri}{index}{suffix}";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    pub base_uri: String,
+    /// Token URI of minted NFTs, `{base_uri}`, `{index}`, `{suffix}` and `{token_id}`
+    /// are replaced by their values
+    pub uri_template: String,
+    /// Appended to the token URI by `{suffix}`, e.g. `.json`
+    pub uri_suffix: String,
+    pub token_id_base: String,
+    pub token_id_scheme: TokenIdScheme,
+    /// Receives the mint fee, the buyer keeps it if unset
+    pub withdraw_address: Option<String>,
+    /// Receives the dev fee
+    pub dev_wallet: String,
+    /// Maximum number of NFTs bought or reserved in one transaction
+    pub mint_per_tx: u64,
+    /// Price per NFT in `NATIVE_DENOM` paid to the withdraw address
+    pub mint_fee: u64,
+    /// Price per NFT in `NATIVE_DENOM` paid to the dev wallet
+    pub dev_fee: u64,
+    pub supply_limit: u64,
+    /// Sale start in seconds since epoch
+    pub sale_time: u64,
+    /// Sale end in seconds since epoch, the sale runs until sold out if unset
+    pub sale_end_time: Option<u64>,
+    /// Pauses the sale without changing `sale_time` or `sale_end_time`
+    pub sale_paused: bool,
+    /// If true, burned NFTs no longer count towards `supply_limit`
+    pub burns_free_supply: bool,
+    /// Maximum number of NFTs the owner can reserve, only bound by `supply_limit` if unset
+    pub max_reserved: Option<u64>,
+    /// Minimum number of NFTs to sell for the drop to succeed. Proceeds are held in
+    /// escrow until it is reached, and refunded if the sale ends before.
+    pub soft_cap: Option<u64>,
+    pub purchase_mode: PurchaseMode,
+    /// Maximum number of NFTs bought in one block, unlimited if unset
+    pub max_per_block: Option<u64>,
+    /// Seconds an address has to wait between two purchases, none if unset
+    pub buy_cooldown: Option<u64>,
+    /// How `filtered_operators` restricts approvals and transfers by operators
+    pub operator_filter: OperatorFilter,
+    /// Manages the blocklist along with the owner
+    pub compliance: Option<String>,
+    /// Compressed secp256k1 public key signing `BuyWithVoucher` vouchers
+    pub voucher_signer: Option<Binary>,
+}
+
+impl Config {
+    pub fn token_uri(&self, token_id: &str, index: u64) -> String {
+        render_uri(&self.uri_template, &self.base_uri, &self.uri_suffix, token_id, index)
+    }
+
+    /// Status of the sale at `now` seconds since epoch
+    pub fn sale_status(&self, sale_state: &SaleState, now: u64) -> SaleStatus {
+        if sale_state.remaining_supply(self) == 0 {
+            SaleStatus::SoldOut
+        } else if self.sale_end_time.map_or(false, |end| end <= now) {
+            SaleStatus::Ended
+        } else if self.sale_paused {
+            SaleStatus::Paused
+        } else if self.sale_time > now {
+            SaleStatus::NotStarted
+        } else {
+            SaleStatus::Active
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SaleStatus {
+    NotStarted,
+    Active,
+    Paused,
+    Ended,
+    /// `supply_limit` has been reached
+    SoldOut,
+}
+
+pub fn render_uri(template: &str, base_uri: &str, suffix: &str, token_id: &str, index: u64) -> String {
+    template
+        .replace("{base_uri}", base_uri)
+        .replace("{index}", &index.to_string())
+        .replace("{suffix}", suffix)
+        .replace("{token_id}", token_id)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct SaleState {
+    /// Number of NFTs minted through `Buy`, `Reserve` and counted redemptions
+    pub total_supply: u64,
+    /// Number of NFTs minted through `Buy` and `Reserve`, the pool position of the next mint
+    pub mint_cursor: u64,
+    pub reserved_amount: u64,
+    /// Number of NFTs ever minted, including redemptions
+    pub minted: u64,
+    /// Number of NFTs ever burned, including redemption inputs
+    pub burned: u64,
+    /// Set once the escrowed proceeds have been released
+    pub funds_released: bool,
+    /// Height of the last block NFTs were bought in
+    pub block_height: u64,
+    /// Number of NFTs bought in `block_height`
+    pub block_minted: u64,
+}
+
+impl SaleState {
+    /// Number of NFTs taking up `supply_limit`
+    pub fn used_supply(&self, config: &Config) -> u64 {
+        if config.burns_free_supply {
+            self.total_supply.saturating_sub(self.burned)
+        } else {
+            self.total_supply
+        }
+    }
+
+    pub fn remaining_supply(&self, config: &Config) -> u64 {
+        config.supply_limit.saturating_sub(self.used_supply(config))
+    }
+
+    /// Number of NFTs that can still be reserved, `None` if uncapped
+    pub fn remaining_reserved(&self, config: &Config) -> Option<u64> {
+        config
+            .max_reserved
+            .map(|max_reserved| max_reserved.saturating_sub(self.reserved_amount))
+    }
+
+    pub fn circulating(&self) -> u64 {
+        self.minted - self.burned
+    }
+
+    /// Whether sale proceeds are held in escrow instead of being paid out
+    pub fn escrow_active(&self, config: &Config) -> bool {
+        config.soft_cap.is_some() && !self.funds_released
+    }
+}
+
+/// Sale totals. CW20 amounts are listed with the token contract address as denom.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct SaleStats {
+    /// Number of distinct buyers
+    pub buyers: u64,
+    /// NFTs sold through `Buy`
+    pub sold: u64,
+    /// NFTs minted through `Reserve`
+    pub reserved: u64,
+    pub revenue: Vec<Coin>,
+    pub mint_fees: Vec<Coin>,
+    pub dev_fees: Vec<Coin>,
+    pub cw20_revenue: Vec<Coin>,
+    pub cw20_mint_fees: Vec<Coin>,
+    pub cw20_dev_fees: Vec<Coin>,
+}
+
+/// Purchases of one buyer. CW20 amounts are listed with the token contract address as denom.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct BuyerStats {
+    pub bought: u64,
+    pub spent: Vec<Coin>,
+    pub cw20_spent: Vec<Coin>,
+}
+
+/// Fees held in escrow in one denom
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EscrowedPayment {
+    pub denom: Denom,
+    pub mint_fee: Uint128,
+    pub dev_fee: Uint128,
+}
+
+impl EscrowedPayment {
+    pub fn total(&self) -> Uint128 {
+        self.mint_fee + self.dev_fee
+    }
+}
+
+/// Purchases of one buyer held in escrow, refunded by burning `token_ids`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Escrow {
+    pub token_ids: Vec<String>,
+    pub payments: Vec<EscrowedPayment>,
+}
+
+fn add_payment(payments: &mut Vec<EscrowedPayment>, denom: &Denom, mint_fee: Uint128, dev_fee: Uint128) {
+    if mint_fee.is_zero() && dev_fee.is_zero() {
+        return;
+    }
+    match payments.iter_mut().find(|p| &p.denom == denom) {
+        Some(p) => {
+            p.mint_fee += mint_fee;
+            p.dev_fee += dev_fee;
+        }
+        None => payments.push(EscrowedPayment {
+            denom: denom.clone(),
+            mint_fee,
+            dev_fee,
+        }),
+    }
+}
+
+fn add_amount(coins: &mut Vec<Coin>, denom: &str, amount: Uint128) {
+    if amount.is_zero() {
+        return;
+    }
+    match coins.iter_mut().find(|c| c.denom == denom) {
+        Some(c) => c.amount += amount,
+        None => coins.push(Coin {
+            denom: denom.to_string(),
+            amount,
+        }),
+    }
+}
+
+fn sub_amount(coins: &mut Vec<Coin>, denom: &str, amount: Uint128) {
+    if let Some(c) = coins.iter_mut().find(|c| c.denom == denom) {
+        c.amount = c.amount.saturating_sub(amount);
+    }
+    coins.retain(|c| !c.amount.is_zero());
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Price {
+    /// Paid to the withdraw address for every NFT bought
+    pub mint_fee: Uint128,
+    /// Paid to the dev wallet for every NFT bought
+    pub dev_fee: Uint128,
+}
+
+impl Price {
+    pub fn total(&self) -> Uint128 {
+        self.mint_fee + self.dev_fee
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Recipe<T> {
+    /// Tokens that have to be burned to redeem this recipe
+    pub inputs: Vec<RecipeInput<T>>,
+    /// Universal resource identifier for the redeemed NFT
+    pub output_uri: Option<String>,
+    /// Extension of the redeemed NFT
+    pub output_extension: T,
+    /// Maximum number of times this recipe can be redeemed, unlimited if unset
+    pub supply_cap: Option<u64>,
+    /// If true, redeemed NFTs count towards `total_supply` and are bound by `supply_limit`
+    pub count_in_supply: bool,
+    /// Number of times this recipe has been redeemed
+    pub redeemed: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RecipeInput<T> {
+    /// Number of tokens required for this input
+    pub count: u64,
+    /// If set, only tokens with exactly this extension are accepted
+    pub extension: Option<T>,
+}
+
 pub struct TokenIndexes<'a, T>
 where
     T: Serialize + DeserializeOwned + Clone,
 {
     pub owner: MultiIndex<'a, Addr, TokenInfo<T>, String>,
+    /// Tokens by mint index, redeemed NFTs come last
+    pub index: MultiIndex<'a, u64, TokenInfo<T>, String>,
+    /// Tokens of each owner by mint index, redeemed NFTs come last
+    pub owner_index: MultiIndex<'a, (Addr, u64), TokenInfo<T>, String>,
 }
 
 impl<'a, T> IndexList<TokenInfo<T>> for TokenIndexes<'a, T>
@@ -187,7 +1011,7 @@ where
     T: Serialize + DeserializeOwned + Clone,
 {
     fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<TokenInfo<T>>> + '_> {
-        let v: Vec<&dyn Index<TokenInfo<T>>> = vec![&self.owner];
+        let v: Vec<&dyn Index<TokenInfo<T>>> = vec![&self.owner, &self.index, &self.owner_index];
         Box::new(v.into_iter())
     }
 }
@@ -196,3 +1020,11 @@ pub fn token_owner_idx<T>(_pk: &[u8], d: &TokenInfo<T>) -> Addr {
     d.owner.clone()
 }
 
+pub fn token_index_idx<T>(_pk: &[u8], d: &TokenInfo<T>) -> u64 {
+    d.index.unwrap_or(u64::MAX)
+}
+
+pub fn token_owner_index_idx<T>(_pk: &[u8], d: &TokenInfo<T>) -> (Addr, u64) {
+    (d.owner.clone(), d.index.unwrap_or(u64::MAX))
+}
+
@@ -1 +1,111 @@
+use cosmwasm_std::{CustomMsg, DepsMut, Response, StdError};
+use semver::Version;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::msg::{ConfigUpdate, MigrateMsg};
+use crate::{ContractError, Cw721Contract, CONTRACT_NAME, CONTRACT_VERSION};
+
 pub mod v0_17;
+pub mod v0_18;
+pub mod v0_19;
+
+/// Name stored by the cw721-base releases dropspace started from
+pub const LEGACY_CONTRACT_NAME: &str = "crates.io:cw721-base";
+
+/// Oldest version that can be migrated from
+pub const MIN_FROM_VERSION: &str = "0.16.0";
+
+/// Runs every upgrade step between the stored version and this one, then applies `msg`
+pub fn migrate<T, C, E, Q>(mut deps: DepsMut, msg: MigrateMsg) -> Result<Response<C>, ContractError>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    C: CustomMsg,
+    Q: CustomMsg,
+    E: CustomMsg,
+{
+    let stored = cw2::get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME && stored.contract != LEGACY_CONTRACT_NAME {
+        return Err(cw2::VersionError::WrongContract {
+            expected: CONTRACT_NAME.to_string(),
+            found: stored.contract,
+        }
+        .into());
+    }
+    let from = parse_version(&stored.version)?;
+    let to = parse_version(CONTRACT_VERSION)?;
+    if from > to {
+        return Err(ContractError::Downgrade {
+            from: stored.version,
+            to: CONTRACT_VERSION.to_string(),
+        });
+    }
+    if from < parse_version(MIN_FROM_VERSION)? {
+        return Err(cw2::VersionError::WrongVersion {
+            expected: MIN_FROM_VERSION.to_string(),
+            found: stored.version,
+        }
+        .into());
+    }
+
+    let mut res = Response::new();
+    if from < Version::new(0, 17, 0) {
+        res = res.add_attributes(v0_17::migrate::<T, C, E, Q>(deps.branch())?.attributes);
+    }
+    // cw721-base never stored the sale settings, whatever its version
+    if from < Version::new(0, 18, 0) || stored.contract == LEGACY_CONTRACT_NAME {
+        res = res.add_attributes(v0_18::migrate::<T, C, E, Q>(deps.branch())?.attributes);
+    }
+    if from < Version::new(0, 19, 0) {
+        let legacy = stored.contract == LEGACY_CONTRACT_NAME;
+        res = res.add_attributes(v0_19::migrate::<T, C, E, Q>(deps.branch(), legacy)?.attributes);
+    }
+    apply_settings::<T, C, E, Q>(deps.branch(), msg)?;
+
+    cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(res
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", stored.version)
+        .add_attribute("to_version", CONTRACT_VERSION))
+}
+
+fn apply_settings<T, C, E, Q>(deps: DepsMut, msg: MigrateMsg) -> Result<(), ContractError>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    C: CustomMsg,
+    Q: CustomMsg,
+    E: CustomMsg,
+{
+    let contract = Cw721Contract::<T, C, E, Q>::default();
+    match msg.history_depth {
+        Some(0) => contract.history_depth.remove(deps.storage),
+        Some(history_depth) => contract.history_depth.save(deps.storage, &history_depth)?,
+        None => {}
+    }
+    let update = ConfigUpdate {
+        base_uri: msg.base_uri,
+        uri_template: msg.uri_template,
+        uri_suffix: msg.uri_suffix,
+        token_id_base: msg.token_id_base,
+        withdraw_address: msg.withdraw_address,
+        dev_wallet: msg.dev_wallet,
+        mint_per_tx: msg.mint_per_tx,
+        mint_fee: msg.mint_fee,
+        dev_fee: msg.dev_fee,
+        supply_limit: msg.supply_limit,
+        sale_time: msg.sale_time,
+        sale_end_time: msg.sale_end_time,
+        burns_free_supply: msg.burns_free_supply,
+        purchase_mode: msg.purchase_mode,
+        max_per_block: msg.max_per_block,
+        buy_cooldown: msg.buy_cooldown,
+        operator_filter: msg.operator_filter,
+    };
+    contract.apply_config_update(deps, update)?;
+    Ok(())
+}
+
+fn parse_version(version: &str) -> Result<Version, ContractError> {
+    Version::parse(version).map_err(|err| {
+        StdError::generic_err(format!("Invalid contract version {}: {}", version, err)).into()
+    })
+}
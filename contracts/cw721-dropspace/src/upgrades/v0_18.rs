@@ -0,0 +1,59 @@
+use cosmwasm_std::{CustomMsg, DepsMut, Response, StdResult, Storage};
+use cw_storage_plus::Item;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{ContractError, Cw721Contract};
+
+// sale settings as stored by 0.18, moved into `Config` and `SaleState` by 0.19
+pub const BASE_URI: Item<String> = Item::new("base_uri");
+pub const TOKEN_ID_BASE: Item<String> = Item::new("token_id_base");
+pub const WITHDRAW_ADDRESS: Item<String> = Item::new("withdraw_address");
+pub const RESERVED_AMOUNT: Item<u64> = Item::new("reserved_amount");
+pub const MINT_PER_TX: Item<u64> = Item::new("mint_per_tx");
+pub const MINT_FEE: Item<u64> = Item::new("mint_fee");
+pub const DEV_FEE: Item<u64> = Item::new("dev_fee");
+pub const SUPPLY_LIMIT: Item<u64> = Item::new("supply_limit");
+pub const TOTAL_SUPPLY: Item<u64> = Item::new("total supply");
+pub const DEV_WALLET: Item<String> = Item::new("dev_wallet");
+pub const SALE_TIME: Item<u64> = Item::new("sale_time");
+
+pub fn migrate<T, C, E, Q>(deps: DepsMut) -> Result<Response<C>, ContractError>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    Q: CustomMsg,
+    E: CustomMsg,
+{
+    let contract = Cw721Contract::<T, C, E, Q>::default();
+
+    // initialize the sale settings with the instantiate defaults
+    let owner = cw_ownable::get_ownership(deps.storage)?.owner;
+    let num_tokens = contract.token_count(deps.storage)?;
+    init(deps.storage, &BASE_URI, String::new())?;
+    init(deps.storage, &TOKEN_ID_BASE, String::new())?;
+    init(deps.storage, &MINT_PER_TX, 1)?;
+    init(deps.storage, &MINT_FEE, 0)?;
+    init(deps.storage, &DEV_FEE, 0)?;
+    init(deps.storage, &SUPPLY_LIMIT, 100000)?;
+    init(deps.storage, &TOTAL_SUPPLY, num_tokens)?;
+    init(deps.storage, &RESERVED_AMOUNT, 0)?;
+    init(deps.storage, &SALE_TIME, u64::MAX)?;
+    if let Some(owner) = owner {
+        init(deps.storage, &DEV_WALLET, owner.into_string())?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", "0.17.0")
+        .add_attribute("to_version", "0.18.0"))
+}
+
+fn init<V: Serialize + DeserializeOwned>(
+    storage: &mut dyn Storage,
+    item: &Item<V>,
+    value: V,
+) -> StdResult<()> {
+    if item.may_load(storage)?.is_none() {
+        item.save(storage, &value)?;
+    }
+    Ok(())
+}
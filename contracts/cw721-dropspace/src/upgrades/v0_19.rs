@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{from_json, Addr, CustomMsg, DepsMut, Order, Response, StdResult};
+use cw721_base_016 as v16;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::state::{
+    render_uri, Config, OperatorFilter, PurchaseMode, SaleState, TokenIdScheme, TokenInfo, DEFAULT_URI_TEMPLATE,
+};
+use crate::upgrades::v0_18;
+use crate::{ContractError, Cw721Contract};
+
+/// `legacy` is set for cw721-base contracts, whose owner index is stored under another key
+pub fn migrate<T, C, E, Q>(deps: DepsMut, legacy: bool) -> Result<Response<C>, ContractError>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    Q: CustomMsg,
+    E: CustomMsg,
+{
+    let contract = Cw721Contract::<T, C, E, Q>::default();
+
+    // move the sale settings and counters into `Config` and `SaleState`
+    let config = Config {
+        base_uri: v0_18::BASE_URI.load(deps.storage)?,
+        uri_template: DEFAULT_URI_TEMPLATE.to_string(),
+        uri_suffix: String::new(),
+        token_id_base: v0_18::TOKEN_ID_BASE.load(deps.storage)?,
+        token_id_scheme: TokenIdScheme::Hash,
+        withdraw_address: v0_18::WITHDRAW_ADDRESS.may_load(deps.storage)?,
+        dev_wallet: v0_18::DEV_WALLET.load(deps.storage)?,
+        mint_per_tx: v0_18::MINT_PER_TX.load(deps.storage)?,
+        mint_fee: v0_18::MINT_FEE.load(deps.storage)?,
+        dev_fee: v0_18::DEV_FEE.load(deps.storage)?,
+        supply_limit: v0_18::SUPPLY_LIMIT.load(deps.storage)?,
+        sale_time: v0_18::SALE_TIME.load(deps.storage)?,
+        burns_free_supply: false,
+        sale_end_time: None,
+        sale_paused: false,
+        max_reserved: None,
+        soft_cap: None,
+        purchase_mode: PurchaseMode::Direct,
+        max_per_block: None,
+        buy_cooldown: None,
+        operator_filter: OperatorFilter::Off,
+        compliance: None,
+        voucher_signer: None,
+    };
+    contract.config.save(deps.storage, &config)?;
+    // 0.18 had no redemptions, so every token ever minted counts towards the total supply
+    let total_supply = v0_18::TOTAL_SUPPLY.load(deps.storage)?;
+    let sale_state = SaleState {
+        total_supply,
+        mint_cursor: total_supply,
+        reserved_amount: v0_18::RESERVED_AMOUNT.load(deps.storage)?,
+        minted: total_supply,
+        burned: total_supply.saturating_sub(contract.token_count(deps.storage)?),
+        funds_released: false,
+        block_height: 0,
+        block_minted: 0,
+    };
+    contract.sale_state.save(deps.storage, &sale_state)?;
+    v0_18::BASE_URI.remove(deps.storage);
+    v0_18::TOKEN_ID_BASE.remove(deps.storage);
+    v0_18::WITHDRAW_ADDRESS.remove(deps.storage);
+    v0_18::DEV_WALLET.remove(deps.storage);
+    v0_18::MINT_PER_TX.remove(deps.storage);
+    v0_18::MINT_FEE.remove(deps.storage);
+    v0_18::DEV_FEE.remove(deps.storage);
+    v0_18::SUPPLY_LIMIT.remove(deps.storage);
+    v0_18::SALE_TIME.remove(deps.storage);
+    v0_18::TOTAL_SUPPLY.remove(deps.storage);
+    v0_18::RESERVED_AMOUNT.remove(deps.storage);
+
+    // 0.18 named every minted token "{token_id_base} #{index}" and built its URI
+    // with `format!("{:?}/{}", base_uri, index)`, which wraps the base URI in quotes.
+    // Every token is loaded and saved once, rebuilding the indexes and the balances
+    let legacy_tokens = v16::Cw721Contract::<T, C, E, Q>::default().tokens;
+    let tokens = contract
+        .tokens
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(String, TokenInfo<T>)>>>()?;
+    let mut balances: BTreeMap<Addr, u64> = BTreeMap::new();
+    let mut indexed = 0u64;
+    let mut repaired = 0u64;
+    for (token_id, mut token) in tokens {
+        let index = TokenIdScheme::Hash.index(&config.token_id_base, &token_id);
+        let token_uri = token
+            .token_uri
+            .as_deref()
+            .and_then(|uri| repair_uri(&config, &token_id, uri));
+        if let Some(index) = index {
+            token.index = Some(index);
+            indexed += 1;
+        }
+        if token_uri.is_some() {
+            token.token_uri = token_uri;
+            repaired += 1;
+        }
+        // removing through cw721-base drops its owner index entries
+        if legacy {
+            legacy_tokens.remove(deps.storage, &token_id)?;
+        }
+        // saving every token also builds the mint index entries of `TokenIndexes`
+        contract.tokens.save(deps.storage, &token_id, &token)?;
+        *balances.entry(token.owner).or_default() += 1;
+    }
+
+    let holders = save_balances::<T, C, E, Q>(deps, &balances)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", "0.18.0")
+        .add_attribute("to_version", "0.19.0")
+        .add_attribute("holders", holders.to_string())
+        .add_attribute("indexed", indexed.to_string())
+        .add_attribute("repaired_uris", repaired.to_string()))
+}
+
+/// Renders the URI of a token minted with a quote-wrapped `"{base_uri}"/{index}` URI
+/// through the URI template, `None` if the URI isn't quote-wrapped
+fn repair_uri(config: &Config, token_id: &str, uri: &str) -> Option<String> {
+    let (quoted, index) = uri.rsplit_once("\"/")?;
+    let base_uri: String = from_json(format!("{}\"", quoted)).ok()?;
+    let index = index.parse::<u64>().ok()?;
+    Some(render_uri(
+        &config.uri_template,
+        &base_uri,
+        &config.uri_suffix,
+        token_id,
+        index,
+    ))
+}
+
+/// Saves the balances counted from the tokens, returns the number of holders
+fn save_balances<T, C, E, Q>(deps: DepsMut, balances: &BTreeMap<Addr, u64>) -> StdResult<u64>
+where
+    T: Serialize + DeserializeOwned + Clone,
+    Q: CustomMsg,
+    E: CustomMsg,
+{
+    let contract = Cw721Contract::<T, C, E, Q>::default();
+    for (owner, balance) in balances {
+        contract.balances.save(deps.storage, owner, balance)?;
+    }
+    contract
+        .num_holders
+        .save(deps.storage, &(balances.len() as u64))?;
+    Ok(balances.len() as u64)
+}
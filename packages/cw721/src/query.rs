@@ -1,4 +1,5 @@
 use cosmwasm_schema::cw_serde;
+use cosmwasm_std::Order;
 use cw_utils::Expiration;
 
 #[cw_serde]
@@ -37,7 +38,11 @@ pub enum Cw721QueryMsg {
         /// unset or false will filter out expired items, you must set to true to see them
         include_expired: Option<bool>,
         start_after: Option<String>,
+        /// Only lists items before this one, along with `order` pages in reverse
+        start_before: Option<String>,
         limit: Option<u32>,
+        /// Ascending if unset
+        order: Option<OrderBy>,
     },
     /// Total number of tokens issued
     NumTokens {},
@@ -64,14 +69,22 @@ pub enum Cw721QueryMsg {
     Tokens {
         owner: String,
         start_after: Option<String>,
+        /// Only lists tokens before this one, along with `order` pages in reverse
+        start_before: Option<String>,
         limit: Option<u32>,
+        /// Ascending if unset
+        order: Option<OrderBy>,
     },
     /// With Enumerable extension.
     /// Requires pagination. Lists all token_ids controlled by the contract.
     /// Return type: TokensResponse.
     AllTokens {
         start_after: Option<String>,
+        /// Only lists tokens before this one, along with `order` pages in reverse
+        start_before: Option<String>,
         limit: Option<u32>,
+        /// Ascending if unset
+        order: Option<OrderBy>,
     },
 }
 
@@ -142,8 +155,24 @@ pub struct AllNftInfoResponse<T> {
 
 #[cw_serde]
 pub struct TokensResponse {
-    /// Contains all token_ids in lexicographical ordering
+    /// Contains all token_ids in lexicographical ordering, reversed for `OrderBy::Desc`
     /// If there are more than `limit`, use `start_after` in future queries
-    /// to achieve pagination.
+    /// to achieve pagination, or `start_before` when listing in descending order.
     pub tokens: Vec<String>,
 }
+
+/// Direction paginated queries list their items in
+#[cw_serde]
+pub enum OrderBy {
+    Asc,
+    Desc,
+}
+
+impl From<OrderBy> for Order {
+    fn from(order: OrderBy) -> Self {
+        match order {
+            OrderBy::Asc => Order::Ascending,
+            OrderBy::Desc => Order::Descending,
+        }
+    }
+}
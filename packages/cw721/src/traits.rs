@@ -3,7 +3,8 @@ use serde::Serialize;
 
 use crate::{
     AllNftInfoResponse, ApprovalResponse, ApprovalsResponse, ContractInfoResponse, NftInfoResponse,
-    NumTokensResponse, OperatorResponse, OperatorsResponse, OwnerOfResponse, TokensResponse,
+    NumTokensResponse, OperatorResponse, OperatorsResponse, OrderBy, OwnerOfResponse,
+    TokensResponse,
 };
 use cosmwasm_std::{Binary, CustomMsg, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
 use cw_utils::Expiration;
@@ -114,6 +115,7 @@ where
         include_expired: bool,
     ) -> StdResult<OperatorResponse>;
 
+    #[allow(clippy::too_many_arguments)]
     fn operators(
         &self,
         deps: Deps,
@@ -121,7 +123,9 @@ where
         owner: String,
         include_expired: bool,
         start_after: Option<String>,
+        start_before: Option<String>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
     ) -> StdResult<OperatorsResponse>;
 
     fn approval(
@@ -146,14 +150,18 @@ where
         deps: Deps,
         owner: String,
         start_after: Option<String>,
+        start_before: Option<String>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
     ) -> StdResult<TokensResponse>;
 
     fn all_tokens(
         &self,
         deps: Deps,
         start_after: Option<String>,
+        start_before: Option<String>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
     ) -> StdResult<TokensResponse>;
 
     fn all_nft_info(